@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Command line in the form of `<command> --name value ...`
+#[derive(Debug, Default)]
+pub struct Args {
+    command: Option<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item=String>) -> Result<Args, String> {
+        let mut result = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = args.next().ok_or(format!("Missing value for --{}", name))?;
+                    result.options.insert(name.to_owned(), value);
+                }
+                None if result.command.is_none() => result.command = Some(arg),
+                None => return Err(format!("Unexpected argument: {}", arg)),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(|value| value.as_str())
+    }
+
+    pub fn value<T: FromStr>(&self, name: &str) -> Result<T, String> {
+        self.get(name).ok_or(format!("Missing --{}", name))?
+            .parse::<T>().map_err(|_| format!("Invalid value for --{}", name))
+    }
+}
@@ -2,17 +2,118 @@
 #![allow(unused_imports)]
 #![allow(dead_code)]
 
+mod cli;
 mod parser;
 mod solutions;
-use solutions::day15::{task02, parse_input};
+use solutions::{day13, day15, day16};
+use solutions::day15::{task02, parse_input, SearchConfig};
+use solutions::day16::ValveConfig;
+use crate::cli::Args;
 use crate::parser::parse;
 use tokio;
 
+/// `--config example|puzzle` picks the preset, `--row`, `--min`, `--max` and `--multiplier`
+/// override single values of it.
+fn search_config(args: &Args) -> Result<SearchConfig, String> {
+    let mut config = match args.get("config") {
+        None | Some("puzzle") => SearchConfig::PUZZLE,
+        Some("example") => SearchConfig::EXAMPLE,
+        Some(other) => return Err(format!("Unknown config: {}", other)),
+    };
+    if args.get("row").is_some() {
+        config.row = args.value("row")?;
+    }
+    if args.get("min").is_some() {
+        let min = args.value("min")?;
+        config.min = (min, min);
+    }
+    if args.get("max").is_some() {
+        let max = args.value("max")?;
+        config.max = (max, max);
+    }
+    if args.get("multiplier").is_some() {
+        config.multiplier = args.value("multiplier")?;
+    }
+    Ok(config)
+}
+
+/// `--part 1|2` picks the preset, `--start`, `--minutes`, `--actors` and `--penalty`
+/// override single values of it.
+fn valve_config(args: &Args) -> Result<ValveConfig, String> {
+    let mut config = match args.get("part") {
+        None | Some("1") => ValveConfig::alone(),
+        Some("2") => ValveConfig::with_elephant(),
+        Some(other) => return Err(format!("Unknown part: {}", other)),
+    };
+    if let Some(start) = args.get("start") {
+        config.start = start.to_owned();
+    }
+    if args.get("minutes").is_some() {
+        config.minutes = args.value("minutes")?;
+    }
+    if args.get("actors").is_some() {
+        config.actors = args.value("actors")?;
+    }
+    if args.get("penalty").is_some() {
+        config.penalty = args.value("penalty")?;
+    }
+    Ok(config)
+}
+
+fn workers(args: &Args) -> Result<usize, String> {
+    match args.get("workers") {
+        Some(_) => args.value("workers"),
+        None => Ok(std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)),
+    }
+}
+
+async fn solve(args: &Args) -> Result<(), String> {
+    match args.value::<u8>("day")? {
+        15 => {
+            let config = search_config(args)?;
+            let (sonars, beacons) =
+                parse_input(parse(args.get("input").unwrap_or("resources/day15.in")))?;
+            match args.value::<u8>("part")? {
+                1 => println!("task01: {}", day15::task01(sonars, beacons, &config)),
+                2 => println!("task02: {:?}", task02(sonars, config, workers(args)?).await?),
+                part => return Err(format!("Unknown part: {}", part)),
+            }
+            Ok(())
+        }
+        16 => {
+            let score = day16::wrapper_solve(args.get("input").unwrap_or("resources/day16.in"), &valve_config(args)?)?;
+            println!("score: {}", score);
+            Ok(())
+        }
+        day => Err(format!("solve is not supported for day {}", day)),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), String> {
-    let (sonars, _) =
-        parse_input(parse("resources/day15.in"))?;
-    println!("task02: {:?}", task02(sonars).await);
+    let args = Args::parse(std::env::args().skip(1))?;
+    match args.command() {
+        Some("explain") => match args.value::<u8>("day")? {
+            13 => day13::wrapper_explain(
+                args.get("input").unwrap_or("resources/day13.in"), args.value("pair")?),
+            16 => day16::wrapper_explain(
+                args.get("input").unwrap_or("resources/day16.in"), &valve_config(&args)?),
+            day => Err(format!("explain is not supported for day {}", day)),
+        },
+        Some("dot") => match args.value::<u8>("day")? {
+            16 => day16::wrapper_dot(
+                args.get("input").unwrap_or("resources/day16.in"),
+                args.get("part").map(|_| valve_config(&args)).transpose()?.as_ref()),
+            day => Err(format!("dot is not supported for day {}", day)),
+        },
+        Some("solve") => solve(&args).await,
+        Some(command) => Err(format!("Unknown command: {}", command)),
+        None => {
+            let (sonars, _) =
+                parse_input(parse("resources/day15.in"))?;
+            println!("task02: {:?}", task02(sonars, SearchConfig::default(), workers(&Args::default())?).await?);
 
-    Ok(())
-}
\ No newline at end of file
+            Ok(())
+        }
+    }
+}
@@ -1,113 +1,191 @@
 use std::cmp::Ordering;
+use std::fmt;
+use std::fmt::Formatter;
+use std::hash::{Hash, Hasher};
+use std::slice;
+use std::str::FromStr;
+use serde_json::Value;
+use crate::parser::parse;
 
-type Pair = (Packet, Packet);
-type Val = u16;
+pub type Pair = (Packet, Packet);
+type Val = u64;
 
 #[derive(Debug, Clone)]
-enum Packet {
-    Empty,
-    Num(Val),
-    Packet(Vec<Box<Packet>>)
+pub enum Packet {
+    Int(Val),
+    List(Vec<Packet>)
 }
 
-impl Packet {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    ListStart,
+    ValueOrListEnd,
+    SeparatorOrListEnd,
+    EndOfInput,
+}
 
-    fn new(raw: String) -> Result<Packet, String> {
-        if !raw.starts_with('[') || !raw.ends_with(']') {
-            return Err(format!("Invalid packet: {}", raw));
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::ListStart => write!(f, "'['"),
+            Expected::ValueOrListEnd => write!(f, "digit, '[' or ']'"),
+            Expected::SeparatorOrListEnd => write!(f, "',' or ']'"),
+            Expected::EndOfInput => write!(f, "end of input"),
         }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Unexpected { column: usize, expected: Expected, found: Option<char> },
+    Overflow { column: usize },
+}
 
-        let raw = &raw[1..raw.len() - 1];
-        if raw.is_empty() {
-            return Ok(Packet::Empty);
+impl ParseError {
+    pub fn column(&self) -> usize {
+        match self {
+            ParseError::Unexpected { column, .. } => *column,
+            ParseError::Overflow { column } => *column,
         }
+    }
+}
 
-        if raw.chars().all(|c| c.is_digit(10)) {
-            return Ok(Packet::Num(raw.parse::<Val>().unwrap()));
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Unexpected { column, expected, found: Some(c) } =>
+                write!(f, "column {}: expected {}, found '{}'", column, expected, c),
+            ParseError::Unexpected { column, expected, found: None } =>
+                write!(f, "column {}: expected {}, found end of input", column, expected),
+            ParseError::Overflow { column } =>
+                write!(f, "column {}: integer does not fit in {} bits", column, Val::BITS),
         }
+    }
+}
 
-        let tokens = Packet::tokenizer(raw.to_owned()).into_iter()
-            .map(|token| match token {
-                t if t.starts_with('[') => Packet::new(t.to_owned()),
-                t if t.chars().all(|c| c.is_digit(10)) => Ok(Packet::Num(t.parse::<Val>().unwrap())),
-                _ => Err(format!("Invalid token: {}", token))
-            })
-            .collect::<Result<Vec<_>, _>>()
-            .map(|tokens| tokens.into_iter().map(|t| Box::new(t)).collect::<Vec<_>>() )
-            .map_err(|e| format!("Invalid packet: {} in {}", e, raw));
+impl From<ParseError> for String {
+    fn from(err: ParseError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Single pass recursive descent parser over the raw bytes of one packet line.
+/// Whitespace is allowed between any two tokens.
+struct Parser<'a> {
+    raw: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
 
-        Ok(Packet::Packet(tokens?))
+impl<'a> Parser<'a> {
+    fn new(raw: &'a str) -> Self {
+        Parser { raw, bytes: raw.as_bytes(), pos: 0 }
     }
 
-    fn wrap(&self) -> Packet {
-        match self {
-            Packet::Packet(a) =>
-                Packet::Packet(a.iter().map(|p| Box::new(p.wrap())).collect()),
-            Packet::Num(num) => Packet::Packet(vec![Box::new(Packet::Num(*num))]),
-            Packet::Empty => Packet::Packet(vec![])
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(b) = self.bytes.get(self.pos) {
+            if !b.is_ascii_whitespace() {
+                return Some(*b);
+            }
+            self.pos += 1;
         }
+        None
     }
 
-    fn tokenizer(input: String) -> Vec<String> {
-        let mut tokens = Vec::new();
-        let mut input_iter = input.chars();
-        let mut stack = Vec::new();
+    fn unexpected(&self, expected: Expected) -> ParseError {
+        ParseError::Unexpected {
+            column: self.pos + 1,
+            expected,
+            found: self.raw[self.pos..].chars().next(),
+        }
+    }
 
-        let mut opening = 0; let mut closing = 0;
+    fn packet(&mut self) -> Result<Packet, ParseError> {
+        if self.peek() != Some(b'[') {
+            return Err(self.unexpected(Expected::ListStart));
+        }
+        let packet = self.list()?;
+        match self.peek() {
+            None => Ok(packet),
+            Some(_) => Err(self.unexpected(Expected::EndOfInput)),
+        }
+    }
 
-        while let Some(c) = input_iter.next() {
-            match c {
-                ',' => {
-                    if opening == closing {
-                        tokens.push(stack.iter().collect::<String>());
-                        stack.clear();
-                    } else {
-                        stack.push(c);
-                    }
-                },
-                '[' => {
-                    opening += 1;
-                    stack.push(c);
-                },
-                ']' => {
-                    closing += 1;
-                    stack.push(c);
-                },
-                _ => stack.push(c)
+    fn value(&mut self) -> Result<Packet, ParseError> {
+        match self.peek() {
+            Some(b'[') => self.list(),
+            Some(b) if b.is_ascii_digit() => self.num(),
+            _ => Err(self.unexpected(Expected::ValueOrListEnd)),
+        }
+    }
+
+    fn num(&mut self) -> Result<Packet, ParseError> {
+        let column = self.pos + 1;
+        let mut val: Val = 0;
+        while let Some(b) = self.bytes.get(self.pos).filter(|b| b.is_ascii_digit()) {
+            val = val.checked_mul(10)
+                .and_then(|val| val.checked_add((b - b'0') as Val))
+                .ok_or(ParseError::Overflow { column })?;
+            self.pos += 1;
+        }
+        Ok(Packet::Int(val))
+    }
+
+    fn list(&mut self) -> Result<Packet, ParseError> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Packet::List(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Packet::List(items));
+                }
+                _ => return Err(self.unexpected(Expected::SeparatorOrListEnd)),
+            }
+        }
+    }
+}
+
+impl Packet {
+
+    fn new(raw: &str) -> Result<Packet, ParseError> {
+        Parser::new(raw).packet()
+    }
+
+    fn wrap(&self) -> Packet {
+        match self {
+            Packet::Int(_) => Packet::List(vec![self.clone()]),
+            Packet::List(_) => self.clone(),
+        }
+    }
+
+    /// Value an integer-like packet compares equal to: `5`, `[5]` and `[[5]]` are all
+    /// the same packet under the puzzle ordering.
+    fn as_int(&self) -> Option<Val> {
+        match self {
+            Packet::Int(num) => Some(*num),
+            Packet::List(items) => match items.as_slice() {
+                [item] => item.as_int(),
+                _ => None,
             }
         }
-        tokens.push(stack.iter().collect::<String>());
-        tokens
     }
 }
 
 impl Ord for Packet {
     fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
-            (Packet::Empty, Packet::Empty) => Ordering::Equal,
-            (Packet::Empty, _) => Ordering::Less,
-            (_, Packet::Empty) => Ordering::Greater,
-            (Packet::Num(a), Packet::Num(b)) => a.cmp(b),
-            (Packet::Num(_), Packet::Packet(_)) => self.wrap().cmp(other),
-            (Packet::Packet(_), Packet::Num(_)) => self.cmp(&other.wrap()),
-            (Packet::Packet(a), Packet::Packet(b)) => {
-                let ordering = a.into_iter().zip(b.into_iter())
-                    .map(|(a, b)| a.cmp(b))
-                    .find(|&ord| ord != Ordering::Equal)
-                    .unwrap_or(Ordering::Equal);
-                match ordering {
-                    Ordering::Equal => {
-                        if a.len() < b.len() {
-                            Ordering::Less
-                        } else if a.len() > b.len() {
-                            Ordering::Greater
-                        } else {
-                            Ordering::Equal
-                        }
-                    }
-                    ord => ord
-                }
-            }
+            (Packet::Int(a), Packet::Int(b)) => a.cmp(b),
+            (Packet::Int(_), Packet::List(b)) => slice::from_ref(self).cmp(b.as_slice()),
+            (Packet::List(a), Packet::Int(_)) => a.as_slice().cmp(slice::from_ref(other)),
+            (Packet::List(a), Packet::List(b)) => a.cmp(b),
         }
     }
 }
@@ -120,24 +198,82 @@ impl PartialOrd for Packet {
 
 impl PartialEq for Packet {
     fn eq(&self, other: &Self) -> bool {
-        match (self,other) {
-            (Packet::Empty, Packet::Empty) => true,
-            (Packet::Empty, _) => false,
-            (_, Packet::Empty) => false,
-            (Packet::Num(a), Packet::Num(b)) => a == b,
-            (Packet::Num(_), Packet::Packet(_)) => self.clone().wrap() == *other,
-            (Packet::Packet(_), Packet::Num(_)) => self == &other.wrap(),
-            (Packet::Packet(a), Packet::Packet(b)) => {
-                a.into_iter().zip(b.into_iter())
-                    .all(|(a, b)| a == b)
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Packet {}
+
+impl Hash for Packet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match (self, self.as_int()) {
+            (_, Some(num)) => {
+                state.write_u8(0);
+                num.hash(state);
+            }
+            (Packet::List(items), None) => {
+                state.write_u8(1);
+                state.write_usize(items.len());
+                items.iter().for_each(|item| item.hash(state));
             }
+            (Packet::Int(_), None) => unreachable!(),
         }
     }
 }
 
-impl Eq for Packet {}
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Packet::Int(num) => write!(f, "{}", num),
+            Packet::List(items) => {
+                write!(f, "[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl FromStr for Packet {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Packet::new(s)
+    }
+}
+
+impl From<&Packet> for Value {
+    fn from(packet: &Packet) -> Self {
+        match packet {
+            Packet::Int(num) => Value::from(*num),
+            Packet::List(items) => Value::Array(items.iter().map(Value::from).collect()),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Packet {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(num) => num.as_u64()
+                .map(Packet::Int)
+                .ok_or(format!("Invalid packet number: {}", num)),
+            Value::Array(items) => items.iter()
+                .map(Packet::try_from)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Packet::List),
+            _ => Err(format!("Invalid packet value: {}", value)),
+        }
+    }
+}
 
-fn task01(input: Vec<Pair>) -> u16 {
+pub fn task01(input: Vec<Pair>) -> u16 {
     let res = input.into_iter().enumerate()
         .map(|(idx, (a, b))| {
             if a < b {
@@ -147,47 +283,433 @@ fn task01(input: Vec<Pair>) -> u16 {
     res.into_iter().sum()
 }
 
-fn task02(input: Vec<Pair>) -> u16 {
-    let mut res = input.into_iter()
-        .chain(vec![
-            (Packet::Num(2).wrap(), Packet::Num(6).wrap())
-        ])
-        .flat_map(|(a, b)| vec![a, b])
-        .collect::<Vec<_>>();
-    res.sort();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepKind {
+    Compare(Packet, Packet),
+    /// Integer on `Side` was promoted to a list with `Packet::wrap` before retrying
+    Wrap(Side, Packet),
+    Smaller(Side),
+    RanOut(Side),
+}
+
+/// Single step of a comparison, `path` holds the indexes taken into the nested lists
+/// and `depth` the indentation level used by the puzzle statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub depth: usize,
+    pub path: Vec<usize>,
+    pub kind: StepKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    pub ordering: Ordering,
+    pub steps: Vec<Step>,
+}
+
+impl Side {
+    fn verdict(&self) -> &'static str {
+        match self {
+            Side::Left => "so input is in the right order",
+            Side::Right => "so input is NOT in the right order",
+        }
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::Left => write!(f, "left"),
+            Side::Right => write!(f, "right"),
+        }
+    }
+}
+
+impl Step {
+    fn new(depth: usize, path: &[usize], kind: StepKind) -> Self {
+        Step { depth, path: path.to_vec(), kind }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:indent$}- ", "", indent = self.depth * 2)?;
+        match &self.kind {
+            StepKind::Compare(left, right) => write!(f, "Compare {} vs {}", left, right),
+            StepKind::Wrap(side, packet) =>
+                write!(f, "Mixed types; convert {} to {} and retry comparison", side, packet),
+            StepKind::Smaller(Side::Left) => write!(f, "Left side is smaller, {}", Side::Left.verdict()),
+            StepKind::Smaller(Side::Right) => write!(f, "Right side is smaller, {}", Side::Right.verdict()),
+            StepKind::RanOut(Side::Left) => write!(f, "Left side ran out of items, {}", Side::Left.verdict()),
+            StepKind::RanOut(Side::Right) => write!(f, "Right side ran out of items, {}", Side::Right.verdict()),
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for step in self.steps.iter() {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
 
-    let i = res.iter()
-        .position(|p| p.eq(&Packet::Num(2).wrap())).unwrap() as u16 + 1;
-    let j = res.iter()
-        .position(|p| p.eq(&Packet::Num(6).wrap())).unwrap() as u16 + 1;
-    i * j
+/// Compares two packets the same way `Ord` does while recording every step taken.
+pub fn explain(left: &Packet, right: &Packet) -> Explanation {
+    let mut steps = Vec::new();
+    let ordering = explain_step(left, right, 0, &mut vec![], &mut steps);
+    Explanation { ordering, steps }
 }
 
+fn explain_step(left: &Packet, right: &Packet, depth: usize, path: &mut Vec<usize>, steps: &mut Vec<Step>) -> Ordering {
+    steps.push(Step::new(depth, path, StepKind::Compare(left.clone(), right.clone())));
+
+    let (ordering, side) = match (left, right) {
+        (Packet::Int(a), Packet::Int(b)) => (a.cmp(b), StepKind::Smaller as fn(Side) -> StepKind),
+        (Packet::Int(_), Packet::List(_)) => {
+            let left = left.wrap();
+            steps.push(Step::new(depth + 1, path, StepKind::Wrap(Side::Left, left.clone())));
+            return explain_step(&left, right, depth + 1, path, steps);
+        }
+        (Packet::List(_), Packet::Int(_)) => {
+            let right = right.wrap();
+            steps.push(Step::new(depth + 1, path, StepKind::Wrap(Side::Right, right.clone())));
+            return explain_step(left, &right, depth + 1, path, steps);
+        }
+        (Packet::List(a), Packet::List(b)) => {
+            for (idx, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                path.push(idx);
+                let ordering = explain_step(a, b, depth + 1, path, steps);
+                path.pop();
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (a.len().cmp(&b.len()), StepKind::RanOut as fn(Side) -> StepKind)
+        }
+    };
+
+    match ordering {
+        Ordering::Less => steps.push(Step::new(depth + 1, path, side(Side::Left))),
+        Ordering::Greater => steps.push(Step::new(depth + 1, path, side(Side::Right))),
+        Ordering::Equal => {}
+    }
+    ordering
+}
+
+/// 1-based positions the `dividers` would take if they were added to `packets` and the whole
+/// list was sorted, computed in a single pass over `packets` without sorting them.
+/// Packets equal to a divider are placed before it, equal dividers keep their given order.
+pub fn divider_positions<'a>(packets: impl IntoIterator<Item=&'a Packet>, dividers: &[Packet]) -> Vec<usize> {
+    let mut order = (0..dividers.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| dividers[a].cmp(&dividers[b]));
+
+    // buckets[i] counts packets that sort before every divider from order[i] onwards
+    let mut buckets = vec![0usize; dividers.len() + 1];
+    for packet in packets {
+        buckets[order.partition_point(|&idx| dividers[idx] < *packet)] += 1;
+    }
+
+    let mut positions = vec![0; dividers.len()];
+    let mut smaller = 0;
+    for (rank, &idx) in order.iter().enumerate() {
+        smaller += buckets[rank];
+        positions[idx] = smaller + rank + 1;
+    }
+    positions
+}
+
+pub fn task02(input: Vec<Packet>) -> usize {
+    let dividers = [Packet::Int(2).wrap(), Packet::Int(6).wrap()];
+    divider_positions(&input, &dividers)
+        .into_iter()
+        .product()
+}
+
+/// Non blank lines of the input grouped into blocks separated by (possibly many) blank lines,
+/// each line paired with its 1-based line number.
+fn blocks(input: &[String]) -> Vec<Vec<(usize, &str)>> {
+    let mut blocks = vec![vec![]];
+    for (idx, line) in input.iter().enumerate() {
+        match line.trim() {
+            "" if blocks.last().is_some_and(|block| !block.is_empty()) => blocks.push(vec![]),
+            "" => {}
+            line => blocks.last_mut().unwrap().push((idx + 1, line)),
+        }
+    }
+    blocks.retain(|block| !block.is_empty());
+    blocks
+}
+
+/// Parses pairs of packets separated by blank lines, as used by part 1.
+pub fn parse_input(input: Vec<String>) -> Result<Vec<Pair>, String> {
+    blocks(&input).into_iter().enumerate()
+        .map(|(idx, block)| match block.as_slice() {
+            [(l_line, left), (r_line, right)] => {
+                let left = Packet::new(left)
+                    .map_err(|err| format!("Pair {}, line {}: {}", idx + 1, l_line, err))?;
+                let right = Packet::new(right)
+                    .map_err(|err| format!("Pair {}, line {}: {}", idx + 1, r_line, err))?;
+                Ok((left, right))
+            }
+            _ => Err(format!("Pair {}, line {}: expected 2 packets, found {}",
+                             idx + 1, block[0].0, block.len())),
+        })
+        .collect()
+}
+
+/// Parses every non blank line as a packet ignoring the pairing, as used by part 2.
+pub fn parse_packets(input: Vec<String>) -> Result<Vec<Packet>, String> {
+    blocks(&input).into_iter()
+        .flatten()
+        .map(|(line, raw)| Packet::new(raw).map_err(|err| format!("Line {}: {}", line, err)))
+        .collect()
+}
+
+pub fn wrapper_explain(path: &str, pair: usize) -> Result<(), String> {
+    let pairs = parse_input(parse(path))?;
+    let (left, right) = pairs.get(pair.checked_sub(1).ok_or("Pairs are numbered from 1")?)
+        .ok_or(format!("Pair {} not found in {}", pair, path))?;
+    println!("== Pair {} ==", pair);
+    print!("{}", explain(left, right));
+    Ok(())
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::parse;
-
-    fn parse_input(input: Vec<String>) -> Result<Vec<Pair>, String> {
-        input.split(|line| line.is_empty())
-            .map(|lines| {
-                if let [body, tail] = lines {
-                    let body_packet = Packet::new(body.to_owned());
-                    let tail_packet = Packet::new(tail.to_owned());
-                    tail_packet.and_then(|t_p|
-                        body_packet.and_then(|b_p|
-                            Ok((b_p, t_p))
-                        ))
-                } else {
-                    panic!("Invalid input")
+    use crate::solutions::rng::Rng;
+
+    fn input_data() -> Vec<String> {
+        parse("resources/day13.in")
+    }
+
+    fn example_data() -> Vec<String> {
+        [
+            "[1,1,3,1,1]", "[1,1,5,1,1]", "",
+            "[[1],[2,3,4]]", "[[1],4]", "",
+            "[9]", "[[8,7,6]]", "",
+            "[[4,4],4,4]", "[[4,4],4,4,4]", "",
+            "[7,7,7,7]", "[7,7,7]", "",
+            "[]", "[3]", "",
+            "[[[]]]", "[[]]", "",
+            "[1,[2,[3,[4,[5,6,7]]]],8,9]", "[1,[2,[3,[4,[5,6,0]]]],8,9]",
+        ].into_iter().map(|line| line.to_owned()).collect()
+    }
+
+    #[test]
+    fn parser_test() -> Result<(), String> {
+        assert_eq!(Packet::new(" [ 1 ,[ ], [2 ,3]] ")?, Packet::new("[1,[],[2,3]]")?);
+        assert_eq!(Packet::new("[18446744073709551615]")?, Packet::List(vec![Packet::Int(Val::MAX)]));
+        assert_eq!(Packet::new("[18446744073709551616]"), Err(ParseError::Overflow { column: 2 }));
+        assert_eq!(Packet::new("[1,,2]"), Err(ParseError::Unexpected {
+            column: 4, expected: Expected::ValueOrListEnd, found: Some(',')
+        }));
+        assert_eq!(Packet::new("[1 2]"), Err(ParseError::Unexpected {
+            column: 4, expected: Expected::SeparatorOrListEnd, found: Some('2')
+        }));
+        assert_eq!(Packet::new("[[1]"), Err(ParseError::Unexpected {
+            column: 5, expected: Expected::SeparatorOrListEnd, found: None
+        }));
+        assert_eq!(Packet::new("[1]]"), Err(ParseError::Unexpected {
+            column: 4, expected: Expected::EndOfInput, found: Some(']')
+        }));
+        assert_eq!(Packet::new("1"), Err(ParseError::Unexpected {
+            column: 1, expected: Expected::ListStart, found: Some('1')
+        }));
+
+        Ok(())
+    }
+
+    struct Gen(Rng);
+
+    impl Gen {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0.below(bound)
+        }
+
+        fn packet(&mut self, depth: u32) -> Packet {
+            let len = self.next(5);
+            Packet::List((0..len)
+                .map(|_| match self.next(3) {
+                    0 if depth > 0 => self.packet(depth - 1),
+                    1 => Packet::Int(self.next(u64::MAX)),
+                    _ => Packet::Int(self.next(11)),
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn display_test() -> Result<(), String> {
+        let packet = Packet::new("[1, [2,[]], [ [3] ]]")?;
+        assert_eq!(packet.to_string(), "[1,[2,[]],[[3]]]");
+        assert_eq!(Value::from(&packet), serde_json::json!([1, [2, []], [[3]]]));
+        assert!(Packet::try_from(&serde_json::json!([1, -2])).is_err());
+        assert!(Packet::try_from(&serde_json::json!([1, "2"])).is_err());
+
+        Ok(())
+    }
+
+    fn hash_of(packet: &Packet) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        packet.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn equality_test() -> Result<(), String> {
+        assert_eq!(Packet::new("[5]")?.to_string(), "[5]");
+        assert_ne!(Packet::new("[1]")?, Packet::new("[1,2]")?);
+        assert_ne!(Packet::new("[]")?, Packet::new("[[]]")?);
+        assert_ne!(Packet::new("[[1,2]]")?, Packet::new("[1,2]")?);
+        assert_eq!(Packet::new("[[5]]")?, Packet::Int(5));
+        assert_eq!(hash_of(&Packet::new("[[5]]")?), hash_of(&Packet::Int(5)));
+        assert_eq!(Packet::new("[[1],[[2]]]")?, Packet::new("[1,2]")?);
+        assert_eq!(hash_of(&Packet::new("[[1],[[2]]]")?), hash_of(&Packet::new("[1,2]")?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn contract_laws_test() {
+        let mut gen = Gen(Rng::new(0x1312_2022));
+        let packets = (0..150)
+            .map(|_| {
+                let mut packet = gen.packet(2);
+                // keep integers small so that equal packets are actually generated
+                if let Packet::List(items) = &mut packet {
+                    items.iter_mut()
+                        .for_each(|item| if let Packet::Int(num) = item { *num %= 3 });
                 }
+                packet
             })
-            .collect::<Result<Vec<Pair>, String>>()
+            .collect::<Vec<_>>();
+
+        for a in packets.iter() {
+            assert_eq!(a, a);
+            assert_eq!(a.cmp(a), Ordering::Equal);
+            for b in packets.iter() {
+                assert_eq!(a == b, a.cmp(b) == Ordering::Equal);
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+                if a == b {
+                    assert_eq!(hash_of(a), hash_of(b));
+                }
+                for c in packets.iter().take(40) {
+                    if a <= b && b <= c {
+                        assert!(a <= c);
+                    }
+                }
+            }
+        }
+
+        let mut sorted = packets.clone();
+        sorted.sort();
+        assert!(sorted.windows(2).all(|w| w[0] <= w[1]));
+        assert!(packets.iter().all(|p| sorted.iter().any(|q| q == p)));
     }
 
-    fn input_data() -> Vec<String> {
-        parse("resources/day13.in")
+    #[test]
+    fn round_trip_test() -> Result<(), String> {
+        let mut gen = Gen(Rng::new(0x2022_1213));
+        for _ in 0..1000 {
+            let packet = gen.packet(4);
+            let text = packet.to_string();
+            let json = Value::from(&packet);
+            assert_eq!(text.parse::<Packet>()?.to_string(), text);
+            assert_eq!(Packet::try_from(&json)?.to_string(), text);
+            assert_eq!(json.to_string(), text);
+            assert_eq!(serde_json::from_str::<Value>(&text).map_err(|err| err.to_string())?, json);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn divider_positions_test() -> Result<(), String> {
+        let packets = ["[3]", "[1]", "[2]", "[2]", "[[7]]"].into_iter()
+            .map(Packet::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        let dividers = ["[5]", "[2]", "[0]", "[5]", "[9]"].into_iter()
+            .map(Packet::new)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(divider_positions(&packets, &dividers), vec![7, 5, 1, 8, 10]);
+        assert_eq!(divider_positions(&packets, &[]), Vec::<usize>::new());
+
+        let mut combined = packets.iter().chain(dividers.iter()).collect::<Vec<_>>();
+        combined.sort();
+        for (divider, position) in dividers.iter().zip(divider_positions(&packets, &dividers)) {
+            assert_eq!(combined[position - 1], divider);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn explain_test() -> Result<(), String> {
+        let explanation = explain(&Packet::new("[[1],[2,3,4]]")?, &Packet::new("[[1],4]")?);
+        assert_eq!(explanation.ordering, Ordering::Less);
+        assert_eq!(explanation.to_string(), [
+            "- Compare [[1],[2,3,4]] vs [[1],4]",
+            "  - Compare [1] vs [1]",
+            "    - Compare 1 vs 1",
+            "  - Compare [2,3,4] vs 4",
+            "    - Mixed types; convert right to [4] and retry comparison",
+            "    - Compare [2,3,4] vs [4]",
+            "      - Compare 2 vs 4",
+            "        - Left side is smaller, so input is in the right order",
+            "",
+        ].join("\n"));
+        assert_eq!(explanation.steps.last().map(|step| step.path.clone()), Some(vec![1, 0]));
+
+        let explanation = explain(&Packet::new("[7,7,7,7]")?, &Packet::new("[7,7,7]")?);
+        assert_eq!(explanation.ordering, Ordering::Greater);
+        assert_eq!(explanation.steps.last().map(|step| step.to_string()),
+                   Some("  - Right side ran out of items, so input is NOT in the right order".to_owned()));
+
+        let pairs = parse_input(example_data())?;
+        for (left, right) in pairs.iter() {
+            assert_eq!(explain(left, right).ordering, left.cmp(right));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_input_test() -> Result<(), String> {
+        let input = ["", "[1]", " [2] ", "", "", "[3]", "[[4]]", "", ""]
+            .into_iter().map(|line| line.to_owned()).collect::<Vec<_>>();
+        assert_eq!(parse_input(input.clone())?.len(), 2);
+        assert_eq!(parse_packets(input)?.len(), 4);
+
+        let input = ["[1]", "[2]", "", "[3]", "[4]", "[5]"]
+            .into_iter().map(|line| line.to_owned()).collect::<Vec<_>>();
+        assert_eq!(parse_input(input.clone()), Err("Pair 2, line 4: expected 2 packets, found 3".to_owned()));
+        assert_eq!(parse_packets(input)?.len(), 5);
+
+        let input = ["[1]", "[2]", "", "[3]", "[4,]"]
+            .into_iter().map(|line| line.to_owned()).collect::<Vec<_>>();
+        assert_eq!(parse_input(input.clone()),
+                   Err("Pair 2, line 5: column 4: expected digit, '[' or ']', found ']'".to_owned()));
+        assert_eq!(parse_packets(input),
+                   Err("Line 5: column 4: expected digit, '[' or ']', found ']'".to_owned()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn example_test() -> Result<(), String> {
+        assert_eq!(task01(parse_input(example_data())?), 13);
+        assert_eq!(task02(parse_packets(example_data())?), 140);
+
+        Ok(())
     }
 
     #[test]
@@ -201,8 +723,8 @@ mod tests {
 
     #[test]
     fn task02_test() -> Result<(), String> {
-        let input_pairs = parse_input(input_data())?;
-        let res = task02(input_pairs);
+        let packets = parse_packets(input_data())?;
+        let res = task02(packets);
         println!("task02: {}", res);
 
         Ok(())
@@ -1,67 +1,110 @@
 use std::fmt;
 use std::fmt::Formatter;
 
-type Pair = (usize, usize);
+pub type Val = i32;
+pub type Pair = (Val, Val);
 
-#[derive(Debug)]
-struct Line(Vec<Pair>);
+/// Rock path made of horizontal and vertical segments between consecutive points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line(Vec<Pair>);
 
-struct Map(Vec<Vec<u8>>);
+/// Cave cells stored row by row, shifted by `min` so that only the area spanned by the rocks,
+/// the source and (for the infinite floor) the reachable sand triangle is allocated.
+pub struct Map {
+    cells: Vec<Vec<u8>>,
+    min: Pair,
+    source: Pair,
+    floor: Option<Val>,
+}
 
-#[derive(Debug)]
-struct Sand(Pair);
+/// Drops grains one after another, resuming each one from where the previous grain's
+/// fall path was still free instead of starting again at the source.
+pub struct Simulation<'a> {
+    map: &'a mut Map,
+    path: Vec<Pair>,
+    settled: u32,
+    overflowed: bool,
+}
 
 impl Map {
-    fn new(lines: Vec<Line>) -> Map {
-        let max_x = lines.iter().flat_map(|line| line.max_x()).max().unwrap();
-        let max_y = lines.iter().flat_map(|line| line.max_y()).max().unwrap();
+    const SOURCE: Pair = (500, 0);
 
-        let mut map = vec![vec![0u8; max_x + 1]; max_y + 1];
+    pub fn new(lines: Vec<Line>) -> Map {
+        Map::with_source(lines, Self::SOURCE, false)
+    }
+
+    pub fn new_with_floor(lines: Vec<Line>) -> Map {
+        Map::with_source(lines, Self::SOURCE, true)
+    }
 
-        for line in lines {
-            line.0.iter().zip(line.0.iter().skip(1))
-                .for_each(|((x1, y1), (x2, y2))| {
-                    let (x1, x2) = if x1 > x2 { (x2, x1) } else { (x1, x2) };
-                    let (y1, y2) = if y1 > y2 { (y2, y1) } else { (y1, y2) };
-                    for x in *x1..=*x2 {
-                        map[*y1][x] = 1;
-                    }
-                    for y in *y1..=*y2 {
-                        map[y][*x2] = 1;
-                    }
-                });
+    /// With `floor` set the cave gets an infinite floor two rows below the lowest rock,
+    /// otherwise sand leaving the rock area falls into the abyss.
+    pub fn with_source(lines: Vec<Line>, source: Pair, floor: bool) -> Map {
+        let points = || lines.iter().flat_map(|line| line.0.iter());
+        let max_y = points().map(|pair| pair.1).max().unwrap_or(source.1);
+        let floor = if floor { Some(max_y + 2) } else { None };
+
+        // sand spreads at most one cell sideways per row it falls
+        let spread = floor.map(|floor| (floor - source.1).max(0)).unwrap_or(0);
+        let min_x = points().map(|pair| pair.0).chain([source.0 - spread]).min().unwrap();
+        let max_x = points().map(|pair| pair.0).chain([source.0 + spread]).max().unwrap();
+        let min_y = points().map(|pair| pair.1).chain([source.1]).min().unwrap();
+        let max_y = floor.map(|floor| floor - 1).unwrap_or(max_y).max(source.1);
+
+        let mut map = Map {
+            cells: vec![vec![0u8; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize],
+            min: (min_x, min_y),
+            source,
+            floor,
+        };
+
+        for line in lines.iter() {
+            line.cells().for_each(|cell| map.set(&cell, 1));
         }
 
-        Map(map)
+        map
     }
 
-    fn new_with_floor(lines: Vec<Line>) -> Map {
-        let max_x = lines.iter().flat_map(|line| line.max_x()).max().unwrap();
-        let max_y = lines.iter().flat_map(|line| line.max_y()).max().unwrap();
-        println!("max_x: {}, max_y: {}", max_x, max_y);
-        let new_lines = lines.into_iter()
-            .chain(vec![Line(vec![(0, max_y + 2), (max_x*2, max_y + 2)])])
-            .collect::<Vec<_>>();
-        Map::new(new_lines)
+    fn index(&self, pair: &Pair) -> Option<(usize, usize)> {
+        let x = usize::try_from(pair.0 - self.min.0).ok()?;
+        let y = usize::try_from(pair.1 - self.min.1).ok()?;
+        if y < self.cells.len() && x < self.cells[y].len() {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, pair: &Pair, val: u8) {
+        let (x, y) = self.index(pair).unwrap();
+        self.cells[y][x] = val;
     }
 
     fn occupied(&self, pair: &Pair) -> bool {
-        !self.out(pair) && self.0[pair.1][pair.0] > 0
+        if self.floor.is_some_and(|floor| pair.1 >= floor) {
+            return true;
+        }
+        self.index(pair).is_some_and(|(x, y)| self.cells[y][x] > 0)
+    }
+
+    fn rock(&self, pair: &Pair) -> bool {
+        self.index(pair).is_some_and(|(x, y)| self.cells[y][x] == 1)
     }
 
     fn out(&self, pair: &Pair) -> bool {
-        pair.0 >= self.0[0].len() || pair.1 >= self.0.len()
+        self.index(pair).is_none()
     }
 
     fn mark(&mut self, pair: &Pair) {
-        self.0[pair.1][pair.0] = 2;
+        self.set(pair, 2);
     }
 }
 
 impl fmt::Debug for Map {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        for (idx, line) in self.0.iter().enumerate() {
-            write!(f, "{:03}: ", idx)?;
+        writeln!(f, "x: {}..{}", self.min.0, self.min.0 + self.cells[0].len() as Val)?;
+        for (idx, line) in self.cells.iter().enumerate() {
+            write!(f, "{:03}: ", idx as Val + self.min.1)?;
             for c in line {
                 write!(f, "{}", match *c {
                     1 => { '#' }
@@ -76,97 +119,376 @@ impl fmt::Debug for Map {
     }
 }
 
-impl Line {
-    fn max_x(&self) -> Option<usize> {
-        self.0.iter().map(|pair| pair.0).max()
+impl<'a> Simulation<'a> {
+    pub fn new(map: &'a mut Map) -> Self {
+        let path = if map.occupied(&map.source) { vec![] } else { vec![map.source] };
+        Simulation { map, path, settled: 0, overflowed: false }
     }
 
-    fn max_y(&self) -> Option<usize> {
-        self.0.iter().map(|pair| pair.1).max()
+    /// Resting place of the next grain, `None` once sand falls into the abyss
+    /// or the source is blocked.
+    pub fn drop_grain(&mut self) -> Option<Pair> {
+        if self.overflowed {
+            return None;
+        }
+        while let Some(&(x, y)) = self.path.last() {
+            match [(x, y + 1), (x - 1, y + 1), (x + 1, y + 1)].into_iter()
+                .find(|next| !self.map.occupied(next)) {
+                Some(next) if self.map.out(&next) => {
+                    self.overflowed = true;
+                    return None;
+                }
+                Some(next) => self.path.push(next),
+                None => {
+                    self.path.pop();
+                    self.map.mark(&(x, y));
+                    self.settled += 1;
+                    return Some((x, y));
+                }
+            }
+        }
+        None
+    }
+
+    /// Drops up to `grains` grains, returns how many of them came to rest.
+    pub fn run(&mut self, grains: u32) -> u32 {
+        (0..grains).map_while(|_| self.drop_grain()).count() as u32
+    }
+
+    pub fn run_until_blocked(&mut self) -> u32 {
+        while self.drop_grain().is_some() {}
+        self.settled
+    }
+
+    pub fn settled(&self) -> u32 {
+        self.settled
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.overflowed || self.path.is_empty()
     }
 }
 
-impl Sand {
-    fn new(pair: Pair) -> Sand {
-        Sand(pair)
+fn task01(map: &mut Map) -> u32 {
+    Simulation::new(map).run_until_blocked()
+}
+
+/// The floor is part of the map, so both parts run the same simulation.
+fn task02(map: &mut Map) -> u32 {
+    task01(map)
+}
+
+/// Counts the sand that comes to rest above the floor without dropping single grains:
+/// a cell fills up exactly when it is free of rock and one of the three cells above it fills up,
+/// so the resting sand is swept row by row from the source down to the floor.
+fn task02_sweep(map: &Map) -> Result<u32, String> {
+    let floor = map.floor.ok_or("Sweep requires a cave with a floor")?;
+    let width = map.cells[0].len();
+    let (source_x, source_y) = map.source;
+
+    let mut row = vec![false; width];
+    let mut counter = 0u32;
+    if let Some((x, _)) = map.index(&map.source).filter(|_| !map.occupied(&map.source)) {
+        row[x] = true;
+        counter += 1;
     }
 
-    fn transform(self, map: &Map) -> Option<Sand> {
-        let mut sand = self;
-        loop {
-            if map.out(&sand.0) {
-                return None;
+    for y in source_y + 1..floor {
+        // the filled area grows by at most one cell to each side per row
+        let spread = y - source_y;
+        let from = ((source_x - spread - map.min.0).max(0) as usize).min(width);
+        let to = ((source_x + spread - map.min.0 + 1).max(0) as usize).min(width);
+        let mut next = vec![false; width];
+        for x in from..to {
+            let above = row[x]
+                || (x > 0 && row[x - 1])
+                || (x + 1 < width && row[x + 1]);
+            if above && !map.rock(&(x as Val + map.min.0, y)) {
+                next[x] = true;
+                counter += 1;
             }
+        }
+        row = next;
+    }
+    Ok(counter)
+}
 
-            match (sand.0.0, sand.0.1) {
-                (x, y) if !map.occupied(&(x, y + 1)) => {
-                    sand = Sand((x, y + 1));
-                }
-                (x, y) if !map.occupied(&(x - 1, y + 1)) => {
-                    sand = Sand((x - 1, y + 1));
-                }
-                (x, y) if !map.occupied(&(x + 1, y + 1)) => {
-                    sand = Sand((x + 1, y + 1));
-                }
-                _ => return Some(sand)
-            }
+impl Line {
+    pub fn points(&self) -> &[Pair] {
+        &self.0
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item=(Pair, Pair)> + '_ {
+        self.0.iter().zip(self.0.iter().skip(1))
+            .map(|(&a, &b)| (a, b))
+    }
+
+    /// Every cell covered by the path, walked from the first point to the last.
+    pub fn cells(&self) -> impl Iterator<Item=Pair> + '_ {
+        self.0.first().copied().into_iter()
+            .chain(self.segments().flat_map(|((x1, y1), (x2, y2))| {
+                let (dx, dy) = ((x2 - x1).signum(), (y2 - y1).signum());
+                (1..=(x2 - x1).abs() + (y2 - y1).abs()).map(move |i| (x1 + dx * i, y1 + dy * i))
+            }))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Expected(&'static str),
+    ExpectedDigit,
+    Negative,
+    Overflow,
+    TooShort,
+    Diagonal(Pair, Pair),
+}
+
+/// Rock path error at a 1-based `line` and `column`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Expected(token) => write!(f, "expected '{}'", token),
+            ErrorKind::ExpectedDigit => write!(f, "expected digit"),
+            ErrorKind::Negative => write!(f, "negative coordinate"),
+            ErrorKind::Overflow => write!(f, "coordinate too large"),
+            ErrorKind::TooShort => write!(f, "path needs at least two points"),
+            ErrorKind::Diagonal((x1, y1), (x2, y2)) => write!(f, "diagonal segment {},{} -> {},{}", x1, y1, x2, y2),
         }
     }
 }
 
-fn task01(map: &mut Map) -> u16 {
-    let mut counter = 0;
-    while let Some(sand) = Sand::new((500, 0)).transform(&map) {
-        map.mark(&sand.0);
-        counter += 1;
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}, column {}: {}", self.line, self.column, self.kind)
     }
-    counter
 }
 
-fn task02(map: &mut Map) -> u32 {
-    let mut counter = 0u32;
-    while let Some(sand) = Sand::new((500, 0)).transform(&map) {
-        map.mark(&sand.0);
-        counter += 1;
-        if sand.0 == (500, 0) {
-            break;
+impl From<ParseError> for String {
+    fn from(err: ParseError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Scanner for a single `x,y -> x,y -> ...` rock path.
+struct PathParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PathParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.bytes.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
         }
     }
-    counter
+
+    fn error_at(column: usize, kind: ErrorKind) -> ParseError {
+        ParseError { line: 1, column, kind }
+    }
+
+    fn error(&self, kind: ErrorKind) -> ParseError {
+        Self::error_at(self.pos + 1, kind)
+    }
+
+    fn expect(&mut self, token: &'static str) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.bytes[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.error(ErrorKind::Expected(token)))
+        }
+    }
+
+    fn number(&mut self) -> Result<Val, ParseError> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'-') => return Err(self.error(ErrorKind::Negative)),
+            Some(b) if b.is_ascii_digit() => {}
+            _ => return Err(self.error(ErrorKind::ExpectedDigit)),
+        }
+        let start = self.pos;
+        let mut val: Val = 0;
+        while let Some(b) = self.bytes.get(self.pos).filter(|b| b.is_ascii_digit()) {
+            val = val.checked_mul(10)
+                .and_then(|val| val.checked_add((b - b'0') as Val))
+                .ok_or(Self::error_at(start + 1, ErrorKind::Overflow))?;
+            self.pos += 1;
+        }
+        Ok(val)
+    }
+
+    fn point(&mut self) -> Result<(usize, Pair), ParseError> {
+        self.skip_whitespace();
+        let column = self.pos + 1;
+        let x = self.number()?;
+        self.expect(",")?;
+        let y = self.number()?;
+        Ok((column, (x, y)))
+    }
+
+    fn line(&mut self) -> Result<Line, ParseError> {
+        let mut points = vec![self.point()?];
+        loop {
+            self.skip_whitespace();
+            if self.pos == self.bytes.len() {
+                break;
+            }
+            self.expect("->")?;
+            points.push(self.point()?);
+        }
+
+        if points.len() < 2 {
+            return Err(Self::error_at(1, ErrorKind::TooShort));
+        }
+        for window in points.windows(2) {
+            let (_, from) = window[0];
+            let (column, to) = window[1];
+            if from.0 != to.0 && from.1 != to.1 {
+                return Err(Self::error_at(column, ErrorKind::Diagonal(from, to)));
+            }
+        }
+        Ok(Line(points.into_iter().map(|(_, point)| point).collect()))
+    }
+}
+
+/// Parses a single rock path, errors are reported on line 1.
+pub fn parse_line(line: &str) -> Result<Line, ParseError> {
+    PathParser { bytes: line.as_bytes(), pos: 0 }.line()
+}
+
+/// Parses one rock path per line, blank lines are skipped.
+pub fn parse_input(input: Vec<String>) -> Result<Vec<Line>, String> {
+    input.iter().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| parse_line(line)
+            .map_err(|err| ParseError { line: idx + 1, ..err }.into()))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::solutions::rng::Rng;
     use crate::parser::parse;
 
-    fn parse_input(input: Vec<String>) -> Result<Vec<Line>, String> {
-        input.into_iter()
-            .map(|line| {
-                let coords = line.split("->")
-                    .map(|part| part.trim().split(',').collect::<Vec<&str>>())
-                    .map(|part| {
-                        if let [a, b] = part.as_slice() {
-                            let a_parsed = a.parse::<usize>()
-                                .map_err(|err| err.to_string())?;
-                            let b_parsed = b.parse::<usize>()
-                                .map_err(|err| err.to_string())?;
-                            Ok((a_parsed, b_parsed))
-                        } else {
-                            Err("Invalid input".to_owned())
-                        }
-                    }).collect::<Result<Vec<Pair>, String>>();
-                match coords {
-                    Ok(coords) => Ok(Line(coords)),
-                    Err(err) => Err(err)
-                }
-            }).collect()
-    }
-
     fn input_data() -> Vec<String> {
         parse("resources/day14.in")
     }
 
+    fn example_data() -> Vec<String> {
+        vec!["498,4 -> 498,6 -> 496,6".to_owned(), "503,4 -> 502,4 -> 502,9 -> 494,9".to_owned()]
+    }
+
+    #[test]
+    fn parse_input_test() -> Result<(), String> {
+        let lines = parse_input(vec!["  498,4->498, 6  ->  496,6 ".to_owned(), "".to_owned()])?;
+        assert_eq!(lines, vec![Line(vec![(498, 4), (498, 6), (496, 6)])]);
+        assert_eq!(lines[0].cells().count(), 5);
+
+        let error = |line: &str| parse_input(vec!["1,1 -> 1,2".to_owned(), line.to_owned()]).unwrap_err();
+        assert_eq!(error("1,1 -> 2,2"), "Line 2, column 8: diagonal segment 1,1 -> 2,2");
+        assert_eq!(error("1,1"), "Line 2, column 1: path needs at least two points");
+        assert_eq!(error("1,1 -> -1,1"), "Line 2, column 8: negative coordinate");
+        assert_eq!(error("1,1 -> 1,-1"), "Line 2, column 10: negative coordinate");
+        assert_eq!(error("1,1 - 1,2"), "Line 2, column 5: expected '->'");
+        assert_eq!(error("1;1 -> 1,2"), "Line 2, column 2: expected ','");
+        assert_eq!(error("1,1 -> 1,99999999999"), "Line 2, column 10: coordinate too large");
+
+        assert_eq!(parse_line("1,1 -> 2,2"), Err(ParseError { line: 1, column: 8, kind: ErrorKind::Diagonal((1, 1), (2, 2)) }));
+        assert_eq!(parse_line("1 1"), Err(ParseError { line: 1, column: 3, kind: ErrorKind::Expected(",") }));
+        assert_eq!(parse_line("1,x"), Err(ParseError { line: 1, column: 3, kind: ErrorKind::ExpectedDigit }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn example_test() -> Result<(), String> {
+        assert_eq!(task01(&mut Map::new(parse_input(example_data())?)), 24);
+        assert_eq!(task02(&mut Map::new_with_floor(parse_input(example_data())?)), 93);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sweep_test() -> Result<(), String> {
+        let map = Map::new_with_floor(parse_input(example_data())?);
+        assert_eq!(task02_sweep(&map)?, 93);
+        assert!(task02_sweep(&Map::new(parse_input(example_data())?)).is_err());
+
+        // a source on or below the floor holds no sand
+        let lines = parse_input(vec!["498,0 -> 502,0".to_owned()])?;
+        for source in [(500, 2), (500, 5)] {
+            let map = Map::with_source(lines.clone(), source, true);
+            assert_eq!(task02_sweep(&map)?, 0);
+            assert_eq!(task02(&mut Map::with_source(lines.clone(), source, true)), 0);
+        }
+
+        // random caves, the sweep has to agree with dropping grains one by one
+        let mut rng = Rng::new(14);
+        for _ in 0..50 {
+            let lines = (0..rng.below(6) + 1)
+                .map(|_| {
+                    let (x, y) = (490 + rng.below(20), 1 + rng.below(12));
+                    let end = if rng.below(2) == 0 { (x + rng.below(5) + 1, y) } else { (x, y + rng.below(5) + 1) };
+                    format!("{},{} -> {},{}", x, y, end.0, end.1)
+                })
+                .collect::<Vec<_>>();
+            let source = (495 + rng.below(10), rng.below(16));
+            let map = Map::with_source(parse_input(lines.clone())?, source, true);
+            let mut simulated = Map::with_source(parse_input(lines.clone())?, source, true);
+            assert_eq!(task02_sweep(&map)?, task02(&mut simulated), "{:?}", lines);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn simulation_test() -> Result<(), String> {
+        let mut map = Map::new(parse_input(example_data())?);
+        let mut simulation = Simulation::new(&mut map);
+        assert_eq!(simulation.drop_grain(), Some((500, 8)));
+        assert_eq!(simulation.drop_grain(), Some((499, 8)));
+        assert_eq!(simulation.run(20), 20);
+        assert_eq!(simulation.settled(), 22);
+        assert!(!simulation.is_blocked());
+        assert_eq!(simulation.run(20), 2);
+        assert!(simulation.is_blocked());
+        assert_eq!(simulation.run_until_blocked(), 24);
+        assert_eq!(simulation.drop_grain(), None);
+
+        let mut map = Map::new_with_floor(parse_input(example_data())?);
+        let mut simulation = Simulation::new(&mut map);
+        assert_eq!(simulation.run(1000), 93);
+        assert!(simulation.is_blocked());
+
+        Ok(())
+    }
+
+    #[test]
+    fn offset_test() -> Result<(), String> {
+        // pile spreads to negative x, far away from the only rock
+        let lines = parse_input(vec!["10,3 -> 11,3".to_owned()])?;
+        let mut map = Map::with_source(lines, (0, 0), true);
+        assert_eq!(task02(&mut map), 25);
+
+        let lines = parse_input(vec!["0,2 -> 4,2".to_owned()])?;
+        let mut map = Map::with_source(lines, (0, 0), false);
+        assert_eq!(task01(&mut map), 0);
+        let lines = parse_input(vec!["0,2 -> 4,2".to_owned()])?;
+        let mut map = Map::with_source(lines, (2, 0), false);
+        assert_eq!(task01(&mut map), 4);
+
+        Ok(())
+    }
+
     #[test]
     fn task01_test() -> Result<(), String> {
         let input_pairs = parse_input(input_data())?;
@@ -1,12 +1,14 @@
 use std::collections::HashSet;
-use std::future::Future;
-use std::sync::Arc;
-use fancy_regex::Regex;
-use futures::{FutureExt, StreamExt};
-use tokio::runtime::Builder;
+use std::fmt::{self, Formatter};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::thread;
+use futures::future::join_all;
 
-type Size = i64;
-type Point = (Size, Size);
+pub type Size = i64;
+pub type Point = (Size, Size);
+/// Line `x = a + s * y` as `(a, s)`
+type RowLine = (Size, Size);
 
 #[derive(Debug, Clone)]
 pub struct Sonar {
@@ -15,28 +17,154 @@ pub struct Sonar {
     radius: Size,
 }
 
-impl Sonar {
-    const LIMIT: Size = 4000000;
-    fn boundary(&self) -> (Size, Size, Size, Size) {
-        (self.x - self.radius, self.x + self.radius, self.y - self.radius, self.y + self.radius)
+/// Cells covered on a single row: merged, sorted and disjoint inclusive intervals
+/// together with the number of cells in them that cannot hold a beacon.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowCoverage {
+    pub intervals: Vec<(Size, Size)>,
+    pub count: Size,
+}
+
+/// Row inspected by part 1, inclusive `min`..`max` search rectangle of part 2
+/// and the x multiplier of the tuning frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchConfig {
+    pub row: Size,
+    pub min: Point,
+    pub max: Point,
+    pub multiplier: Size,
+}
+
+impl SearchConfig {
+    pub const PUZZLE: SearchConfig = SearchConfig {
+        row: 2000000,
+        min: (0, 0),
+        max: (4000000, 4000000),
+        multiplier: 4000000,
+    };
+
+    pub const EXAMPLE: SearchConfig = SearchConfig {
+        row: 10,
+        min: (0, 0),
+        max: (20, 20),
+        multiplier: 4000000,
+    };
+
+    pub fn contains(&self, point: &Point) -> bool {
+        self.min.0 <= point.0 && point.0 <= self.max.0 && self.min.1 <= point.1 && point.1 <= self.max.1
+    }
+
+    pub fn frequency(&self, point: &Point) -> Size {
+        point.0 * self.multiplier + point.1
+    }
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig::PUZZLE
+    }
+}
+
+/// Rectangle `u.0..=u.1`, `v.0..=v.1` in rotated coordinates (`u = x + y`, `v = x - y`)
+/// that no sonar covers, clipped to the `min`..`max` box it was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub u: (Size, Size),
+    pub v: (Size, Size),
+    pub cells: Size,
+    min: Point,
+    max: Point,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    pub uncovered: Vec<Region>,
+    pub covered: Size,
+}
+
+impl Region {
+    fn new(u: (Size, Size), v: (Size, Size), (min, max): (Point, Point)) -> Self {
+        let mut region = Region { u, v, cells: 0, min, max };
+        region.cells = region.count();
+        region
+    }
+
+    /// Lines bounding the x range of row `y` from below and from above, each as `a + s * y`.
+    fn row_lines(&self) -> ([RowLine; 3], [RowLine; 3]) {
+        ([(self.min.0, 0), (self.u.0, -1), (self.v.0, 1)],
+         [(self.max.0, 0), (self.u.1, -1), (self.v.1, 1)])
     }
 
+    fn row(&self, y: Size) -> (Size, Size) {
+        let (lo, hi) = self.row_lines();
+        (lo.iter().map(|(a, s)| a + s * y).max().unwrap(),
+         hi.iter().map(|(a, s)| a + s * y).min().unwrap())
+    }
+
+    fn row_count(&self, y: Size) -> Size {
+        let (lo, hi) = self.row(y);
+        (hi - lo + 1).max(0)
+    }
+
+    /// Number of cells, summed row by row. The row width is piecewise linear in `y`, so rows are
+    /// only evaluated around the points where two of the bounding lines cross and every piece
+    /// in between is summed as an arithmetic series.
+    fn count(&self) -> Size {
+        let (lo, hi) = self.row_lines();
+        let crossing = |(a1, s1): RowLine, (a2, s2): RowLine, offset: Size| {
+            (s1 != s2).then(|| (a2 - a1 + offset).div_euclid(s1 - s2))
+        };
+        let pairs = |lines: [RowLine; 3]| [(lines[0], lines[1]), (lines[0], lines[2]), (lines[1], lines[2])];
+        let mut breaks = pairs(lo).into_iter().chain(pairs(hi))
+            .filter_map(|(a, b)| crossing(a, b, 0))
+            .chain(lo.iter().flat_map(|a| hi.iter().filter_map(|b| crossing(*a, *b, 1))))
+            .flat_map(|y| [y - 1, y, y + 1, y + 2])
+            .map(|y| y.clamp(self.min.1, self.max.1 + 1))
+            .chain([self.min.1, self.max.1 + 1])
+            .collect::<Vec<_>>();
+        breaks.sort_unstable();
+        breaks.dedup();
+
+        breaks.windows(2)
+            .map(|w| (w[1] - w[0]) * (self.row_count(w[0]) + self.row_count(w[1] - 1)) / 2)
+            .sum()
+    }
+
+    /// Every uncovered cell of the region, row by row.
+    pub fn points(&self) -> impl Iterator<Item=Point> + '_ {
+        (self.min.1..=self.max.1)
+            .flat_map(move |y| {
+                let (lo, hi) = self.row(y);
+                (lo..=hi).map(move |x| (x, y))
+            })
+    }
+}
+
+impl Sonar {
     fn contains(&self, point: &Point) -> bool {
         manhhatan_distance(&(self.x, self.y), point) <= self.radius
     }
 
-    fn boundary_lines(&self) -> impl Iterator<Item=Point> + '_ {
+    /// Inclusive x range the sonar covers on row `y`, if it reaches that row at all.
+    fn row_interval(&self, y: Size) -> Option<(Size, Size)> {
+        let reach = self.radius - (self.y - y).abs();
+        (reach >= 0).then_some((self.x - reach, self.x + reach))
+    }
+
+    fn boundary_lines<'a>(&'a self, config: &'a SearchConfig) -> impl Iterator<Item=Point> + 'a {
         let radius = self.radius + 1;
         (0..radius)
             .map(move |i| (self.x + i, self.y + radius - i))
             .chain((0..radius).map(move |i| (self.x - radius + i, self.y + i)))
             .chain((0..radius).map(move |i| (self.x + radius - i, self.y - i)))
             .chain((0..radius).map(move |i| (self.x - i, self.y - radius + i)))
-            .filter(|point| Sonar::in_search_area(point))
+            .filter(|point| config.contains(point))
     }
 
-    fn in_search_area(point: &Point) -> bool {
-        point.0 >= 0 && point.1 >= 0 && point.0 <= Self::LIMIT && point.1 <= Self::LIMIT
+    /// Lines just outside the sonar range in rotated coordinates, `x + y = u` and `x - y = v`.
+    fn rotated_boundaries(&self) -> ([Size; 2], [Size; 2]) {
+        let (u, v, r) = (self.x + self.y, self.x - self.y, self.radius + 1);
+        ([u - r, u + r], [v - r, v + r])
     }
 }
 
@@ -44,131 +172,593 @@ fn manhhatan_distance(a: &Point, b: &Point) -> Size {
     (a.0 - b.0).abs() + (a.1 - b.1).abs()
 }
 
-fn get_coord(cap: &fancy_regex::Captures, index: usize) -> Result<Size, String> {
-    cap.get(index)
-        .ok_or("Not found".to_string())?.as_str().parse::<Size>()
-        .map_err(|err| err.to_string())
+fn merge_intervals(mut intervals: Vec<(Size, Size)>) -> Vec<(Size, Size)> {
+    intervals.sort_unstable();
+    let mut merged: Vec<(Size, Size)> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+pub fn row_coverage(sonars: &[Sonar], beacons: &HashSet<Point>, row: Size) -> RowCoverage {
+    let intervals = merge_intervals(sonars.iter()
+        .filter_map(|sonar| sonar.row_interval(row))
+        .collect());
+    let covered = intervals.iter().map(|(start, end)| end - start + 1).sum::<Size>();
+    let beacons = beacons.iter()
+        .filter(|(x, y)| *y == row && intervals.iter().any(|(start, end)| start <= x && x <= end))
+        .count() as Size;
+    RowCoverage { intervals, count: covered - beacons }
 }
 
-fn find_edges(input: &Vec<Sonar>) -> (Size, Size, Size, Size) {
-    let mut min_x = i64::MAX;
-    let mut max_x = i64::MIN;
-    let mut min_y = i64::MAX;
-    let mut max_y = i64::MIN;
+pub fn task01(sonars: Vec<Sonar>, beacons: HashSet<Point>, config: &SearchConfig) -> Size {
+    row_coverage(&sonars, &beacons, config.row).count
+}
 
-    for sonar in input {
-        let (x_min, x_max, y_min, y_max) = sonar.boundary();
-        if x_min < min_x {
-            min_x = x_min;
+async fn search_range(sonars: Arc<Vec<Sonar>>, config: SearchConfig, start: Size, size: Size) -> Option<Size> {
+    for y in start..=(start + size).min(config.max.1) {
+        for x in config.min.0..=config.max.0 {
+            if sonars.iter().all(|sonar| !sonar.contains(&(x, y))) {
+                return Some(config.frequency(&(x, y)));
+            }
         }
-        if x_max > max_x {
-            max_x = x_max;
+    }
+    None
+}
+
+/// Boundary search shared by the workers. Sonars are handed out in index order and the gap
+/// found on the boundary of the lowest sonar wins, so the result does not depend on scheduling:
+/// once a gap is found, sonars with a higher index are skipped or abandoned mid-way.
+struct BoundarySearch {
+    sonars: Vec<Sonar>,
+    config: SearchConfig,
+    next: AtomicUsize,
+    found: AtomicUsize,
+    result: Mutex<Option<(usize, Point)>>,
+}
+
+impl BoundarySearch {
+    const CANCEL_CHECK: usize = 4096;
+
+    fn new(sonars: Vec<Sonar>, config: SearchConfig) -> Self {
+        BoundarySearch {
+            sonars,
+            config,
+            next: AtomicUsize::new(0),
+            found: AtomicUsize::new(usize::MAX),
+            result: Mutex::new(None),
         }
-        if y_min < min_y {
-            min_y = y_min;
+    }
+
+    fn worker(&self) {
+        loop {
+            let idx = self.next.fetch_add(1, AtomicOrdering::SeqCst);
+            if idx >= self.sonars.len() || idx > self.found.load(AtomicOrdering::SeqCst) {
+                return;
+            }
+            if let Some(point) = self.search(idx) {
+                self.found.fetch_min(idx, AtomicOrdering::SeqCst);
+                let mut result = self.result.lock().unwrap();
+                if result.is_none_or(|(best, _)| idx < best) {
+                    *result = Some((idx, point));
+                }
+            }
         }
-        if y_max > max_y {
-            max_y = y_max;
+    }
+
+    fn search(&self, idx: usize) -> Option<Point> {
+        for (step, point) in self.sonars[idx].boundary_lines(&self.config).enumerate() {
+            if step % Self::CANCEL_CHECK == 0 && self.found.load(AtomicOrdering::SeqCst) < idx {
+                return None;
+            }
+            if self.sonars.iter().all(|sonar| !sonar.contains(&point)) {
+                return Some(point);
+            }
         }
+        None
+    }
+
+    fn frequency(&self) -> Option<Size> {
+        self.result.lock().unwrap().map(|(_, point)| self.config.frequency(&point))
     }
-    (min_x, max_x, min_y, max_y)
 }
 
-fn task01(sonars: Vec<Sonar>, beacons: HashSet<Point>) -> Size {
-    let line_y = Sonar::LIMIT;
-    let (min_x, max_x, _, _) = find_edges(&sonars);
-    println!("{} {}", min_x, max_x);
-    (min_x..=max_x)
-        .into_iter()
-        .filter(|idx|
-            sonars.iter().any(|sonar| sonar.contains(&(*idx, line_y)))
-                && !beacons.contains(&(*idx, line_y))
-        )
-        .count() as Size
+/// Finds a point inside the `(min, max)` area that no sonar covers. Unless the area is a single
+/// cell, an uncovered cell has a neighbour in the area that some sonar covers, so it lies on one
+/// of the lines just outside that sonar range (`x + y = u` or `x - y = v` in rotated coordinates).
+/// Each of those lines is merged like a row in `row_coverage` and checked for the first free point.
+pub fn locate_gap(sonars: &[Sonar], (min, max): (Point, Point)) -> Option<Point> {
+    let (mut us, mut vs): (Vec<Size>, Vec<Size>) = (vec![], vec![]);
+    for (u, v) in sonars.iter().map(|sonar| sonar.rotated_boundaries()) {
+        us.extend(u);
+        vs.extend(v);
+    }
+    us.sort_unstable();
+    us.dedup();
+    vs.sort_unstable();
+    vs.dedup();
+
+    // x ranges covered on a line fixing one rotated coordinate to `c`, `rotated` gives
+    // the sonar centre as (fixed, free) rotated coordinates
+    let line_gap = |c: Size, range: (Size, Size), rotated: fn(&Sonar) -> (Size, Size)| {
+        let covered = sonars.iter()
+            .filter_map(|sonar| {
+                let (fixed, free) = rotated(sonar);
+                ((c - fixed).abs() <= sonar.radius).then_some((
+                    -(-(c + free - sonar.radius)).div_euclid(2),
+                    (c + free + sonar.radius).div_euclid(2),
+                ))
+            })
+            .collect();
+        first_gap(covered, range)
+    };
+
+    let on_u = us.iter().filter_map(|&u| {
+        line_gap(u, (min.0.max(u - max.1), max.0.min(u - min.1)), |sonar| (sonar.x + sonar.y, sonar.x - sonar.y))
+            .map(|x| (x, u - x))
+    });
+    let on_v = vs.iter().filter_map(|&v| {
+        line_gap(v, (min.0.max(v + min.1), max.0.min(v + max.1)), |sonar| (sonar.x - sonar.y, sonar.x + sonar.y))
+            .map(|x| (x, x - v))
+    });
+    let corners = [(min.0, min.1), (min.0, max.1), (max.0, min.1), (max.0, max.1)].into_iter()
+        .filter(|point| sonars.iter().all(|sonar| !sonar.contains(point)));
+
+    on_u.chain(on_v).chain(corners).next()
 }
 
-async fn search_range(sonars: Arc<Vec<Sonar>>, start: Size, size: Size) -> Option<Size> {
-    for y in start..=start + size {
-        for x in start..=4000000 {
-            if sonars.iter().all(|sonar| !sonar.contains(&(x, y))) {
-                return Some(x * 4000000 + y);
+/// First value of the inclusive range that none of the `covered` intervals contains.
+fn first_gap(covered: Vec<(Size, Size)>, (lo, hi): (Size, Size)) -> Option<Size> {
+    let mut x = lo;
+    for (start, end) in merge_intervals(covered) {
+        if start > x {
+            break;
+        }
+        x = x.max(end + 1);
+    }
+    (x <= hi).then_some(x)
+}
+
+/// Union of the sonar ranges inside the `min`..`max` box. Every sonar covers a square in
+/// rotated coordinates, so compressing the rotated plane along the square edges leaves a small
+/// grid whose cells are either fully covered or not; the uncovered ones are merged into rectangles.
+pub fn sensor_coverage(sonars: &[Sonar], (min, max): (Point, Point)) -> Coverage {
+    // rotated hull of the box
+    let (u_min, u_max) = (min.0 + min.1, max.0 + max.1);
+    let (v_min, v_max) = (min.0 - max.1, max.0 - min.1);
+    let squares = sonars.iter()
+        .map(|sonar| {
+            let (u, v) = (sonar.x + sonar.y, sonar.x - sonar.y);
+            ((u - sonar.radius, u + sonar.radius), (v - sonar.radius, v + sonar.radius))
+        })
+        .collect::<Vec<_>>();
+
+    let edges = |lo: Size, hi: Size, ranges: Vec<(Size, Size)>| {
+        let mut edges = ranges.into_iter()
+            .flat_map(|(a, b)| [a, b + 1])
+            .map(|e| e.clamp(lo, hi + 1))
+            .chain([lo, hi + 1])
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    };
+    let us = edges(u_min, u_max, squares.iter().map(|(u, _)| *u).collect());
+    let vs = edges(v_min, v_max, squares.iter().map(|(_, v)| *v).collect());
+
+    let is_free = |u: Size, v: Size| squares.iter()
+        .all(|((u1, u2), (v1, v2))| u < *u1 || u > *u2 || v < *v1 || v > *v2);
+
+    // runs of free cells along v for each u stripe, stacked into rectangles while they repeat
+    let mut open: Vec<((Size, Size), (Size, Size))> = vec![];
+    let mut done = vec![];
+    for uw in us.windows(2) {
+        let mut runs: Vec<(Size, Size)> = vec![];
+        for vw in vs.windows(2) {
+            if is_free(uw[0], vw[0]) {
+                match runs.last_mut() {
+                    Some(run) if run.1 + 1 == vw[0] => run.1 = vw[1] - 1,
+                    _ => runs.push((vw[0], vw[1] - 1)),
+                }
             }
         }
+        let (kept, closed): (Vec<_>, Vec<_>) = open.into_iter()
+            .partition(|(_, v)| runs.contains(v));
+        done.extend(closed);
+        open = runs.into_iter()
+            .map(|v| match kept.iter().find(|(_, kept_v)| *kept_v == v) {
+                Some((u, _)) => ((u.0, uw[1] - 1), v),
+                None => ((uw[0], uw[1] - 1), v),
+            })
+            .collect();
     }
-    return None;
+    done.extend(open);
+
+    let uncovered = done.into_iter()
+        .map(|(u, v)| Region::new(u, v, (min, max)))
+        .filter(|region| region.cells > 0)
+        .collect::<Vec<_>>();
+    let area = (max.0 - min.0 + 1) * (max.1 - min.1 + 1);
+    let covered = area - uncovered.iter().map(|region| region.cells).sum::<Size>();
+    Coverage { uncovered, covered }
 }
 
-async fn search_iter(sonars: Arc<Vec<Sonar>>, idx: usize) -> Option<Size> {
-    let boundary = sonars.get(idx).unwrap().boundary_lines().collect::<Vec<_>>();
-    for point in boundary {
-        if sonars.iter().all(|sonar| !sonar.contains(&point)) {
-            println!("result: {:?}", point);
-            return Some(point.0 * 4000000 + point.1);
+/// Distress beacon position and its tuning frequency.
+pub fn task02_rotated(sonars: &[Sonar], config: &SearchConfig) -> Option<(Point, Size)> {
+    locate_gap(sonars, (config.min, config.max))
+        .map(|point| (point, config.frequency(&point)))
+}
+
+/// Walks the sonar boundaries on `workers` scoped threads.
+pub fn task02_threads(sonars: Vec<Sonar>, config: SearchConfig, workers: usize) -> Option<Size> {
+    let search = BoundarySearch::new(sonars, config);
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| search.worker());
         }
+    });
+    search.frequency()
+}
+
+/// Walks the sonar boundaries on `workers` blocking tasks of the caller's runtime, a worker
+/// that fails is reported instead of being taken for a search without a gap.
+pub async fn task02(sonars: Vec<Sonar>, config: SearchConfig, workers: usize) -> Result<Option<Size>, String> {
+    let search = Arc::new(BoundarySearch::new(sonars, config));
+    let handles = (0..workers.max(1))
+        .map(|_| {
+            let search = Arc::clone(&search);
+            tokio::task::spawn_blocking(move || search.worker())
+        })
+        .collect::<Vec<_>>();
+    for result in join_all(handles).await {
+        result.map_err(|err| format!("Search worker failed: {}", err))?;
     }
-    None
+    Ok(search.frequency())
 }
 
-pub async fn task02(sonars: Vec<Sonar>) -> Option<Size> {
-    let runtime = Builder::new_multi_thread()
-        .worker_threads(1)
-        .thread_name("workers")
-        .build()
-        .unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Token(&'static str),
+    Whitespace,
+    Number,
+    EndOfLine,
+}
 
-    let sonars_arc = Arc::new(sonars.clone());
-    let mut fut = sonars.iter().enumerate()
-        .map(|(idx,_)| {
-            let sonars = Arc::clone(&sonars_arc);
-            runtime.spawn(async move {
-                search_iter(sonars, idx).await
-            })
-        }).collect::<Vec<_>>().into_iter();
-    while let Some(result) = fut.next() {
-        if let Ok(value) = result.await {
-            if let Some(value) = value {
-                runtime.shutdown_background();
-                return Some(value)
-            }
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Token(token) => write!(f, "'{}'", token),
+            Expected::Whitespace => write!(f, "whitespace"),
+            Expected::Number => write!(f, "number"),
+            Expected::EndOfLine => write!(f, "end of line"),
         }
     }
-    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Unexpected { expected: Expected, found: Option<char> },
+    Overflow,
+}
+
+/// Reading error at a 1-based `line` and `column`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ErrorKind::Unexpected { expected, found: Some(c) } => write!(f, "expected {}, found '{}'", expected, c),
+            ErrorKind::Unexpected { expected, found: None } => write!(f, "expected {}, found end of line", expected),
+            ErrorKind::Overflow => write!(f, "integer does not fit in {} bits", Size::BITS),
+        }
+    }
+}
+
+impl From<ParseError> for String {
+    fn from(err: ParseError) -> Self {
+        err.to_string()
+    }
+}
+
+/// Scanner for `Sensor at x=.., y=..: closest beacon is at x=.., y=..`, whitespace is allowed
+/// around every token and required after every word.
+struct ReadingParser<'a> {
+    raw: &'a str,
+    line: usize,
+    pos: usize,
+}
+
+impl<'a> ReadingParser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.raw[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn error(&self, expected: Expected) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.pos + 1,
+            kind: ErrorKind::Unexpected { expected, found: self.raw[self.pos..].chars().next() },
+        }
+    }
+
+    fn token(&mut self, token: &'static str) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.raw[self.pos..].starts_with(token) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.error(Expected::Token(token)))
+        }
+    }
+
+    fn word(&mut self, word: &'static str) -> Result<(), ParseError> {
+        self.token(word)?;
+        if self.raw[self.pos..].starts_with(char::is_whitespace) {
+            Ok(())
+        } else {
+            Err(self.error(Expected::Whitespace))
+        }
+    }
+
+    fn number(&mut self) -> Result<Size, ParseError> {
+        self.skip_whitespace();
+        let rest = &self.raw[self.pos..];
+        let sign = rest.starts_with('-') as usize;
+        let digits = rest[sign..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - sign);
+        if digits == 0 {
+            return Err(self.error(Expected::Number));
+        }
+        let value = rest[..sign + digits].parse::<Size>()
+            .map_err(|_| ParseError { line: self.line, column: self.pos + 1, kind: ErrorKind::Overflow })?;
+        self.pos += sign + digits;
+        Ok(value)
+    }
+
+    fn point(&mut self) -> Result<Point, ParseError> {
+        self.token("x")?;
+        self.token("=")?;
+        let x = self.number()?;
+        self.token(",")?;
+        self.token("y")?;
+        self.token("=")?;
+        let y = self.number()?;
+        Ok((x, y))
+    }
+
+    fn reading(&mut self) -> Result<(Sonar, Point), ParseError> {
+        self.word("Sensor")?;
+        self.word("at")?;
+        let (x, y) = self.point()?;
+        self.token(":")?;
+        for word in ["closest", "beacon", "is", "at"] {
+            self.word(word)?;
+        }
+        let beacon = self.point()?;
+        self.skip_whitespace();
+        if self.pos < self.raw.len() {
+            return Err(self.error(Expected::EndOfLine));
+        }
+        Ok((Sonar { x, y, radius: manhhatan_distance(&(x, y), &beacon) }, beacon))
+    }
+}
+
+/// Every sonar together with the closest beacon it detected, blank lines are skipped.
+pub fn parse_readings(input: Vec<String>) -> Result<Vec<(Sonar, Point)>, String> {
+    input.iter().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| ReadingParser { raw: line, line: idx + 1, pos: 0 }.reading().map_err(String::from))
+        .collect()
 }
 
 pub fn parse_input(input: Vec<String>) -> Result<(Vec<Sonar>, HashSet<Point>), String> {
-    let re = Regex::new(r"((?<=at x=)-?\d+).*?((?<=y=)-?\d+)").unwrap();
-    let (sonars, points): (Vec<_>, Vec<_>) = input.into_iter()
-        .map(|line| {
-            let mut caps = re.captures_iter(&line);
-            let first = caps.next().ok_or("Not found".to_string())?
-                .map_err(|err| err.to_string())?;
-            let x = get_coord(&first, 1)?;
-            let y = get_coord(&first, 2)?;
-
-            let second = caps.next().ok_or("Not found".to_string())?
-                .map_err(|err| err.to_string())?;
-            let i = get_coord(&second, 1)?;
-            let j = get_coord(&second, 2)?;
-            Ok((Sonar { x, y, radius: manhhatan_distance(&(x, y), &(i, j)) }, (i, j)))
-        }).collect::<Result<Vec<(Sonar, Point)>, String>>()?
-        .into_iter().unzip();
-
-    Ok((sonars, HashSet::from_iter(points.into_iter())))
+    let (sonars, beacons): (Vec<_>, HashSet<_>) = parse_readings(input)?.into_iter().unzip();
+    Ok((sonars, beacons))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::solutions::rng::Rng;
     use crate::parser::parse;
 
     fn input_data() -> Vec<String> {
         parse("resources/day15_test.in")
     }
 
+    fn example_data() -> Vec<String> {
+        [
+            "Sensor at x=2, y=18: closest beacon is at x=-2, y=15",
+            "Sensor at x=9, y=16: closest beacon is at x=10, y=16",
+            "Sensor at x=13, y=2: closest beacon is at x=15, y=3",
+            "Sensor at x=12, y=14: closest beacon is at x=10, y=16",
+            "Sensor at x=10, y=20: closest beacon is at x=10, y=16",
+            "Sensor at x=14, y=17: closest beacon is at x=10, y=16",
+            "Sensor at x=8, y=7: closest beacon is at x=2, y=10",
+            "Sensor at x=2, y=0: closest beacon is at x=2, y=10",
+            "Sensor at x=0, y=11: closest beacon is at x=2, y=10",
+            "Sensor at x=20, y=14: closest beacon is at x=25, y=17",
+            "Sensor at x=17, y=20: closest beacon is at x=21, y=22",
+            "Sensor at x=16, y=7: closest beacon is at x=15, y=3",
+            "Sensor at x=14, y=3: closest beacon is at x=15, y=3",
+            "Sensor at x=20, y=1: closest beacon is at x=15, y=3",
+        ].into_iter().map(|line| line.to_owned()).collect()
+    }
+
+    #[test]
+    fn row_coverage_test() -> Result<(), String> {
+        let (sonars, beacons) = parse_input(example_data())?;
+        let coverage = row_coverage(&sonars, &beacons, 10);
+        assert_eq!(coverage, RowCoverage { intervals: vec![(-2, 24)], count: 26 });
+
+        let coverage = row_coverage(&sonars, &beacons, 11);
+        assert_eq!(coverage.intervals, vec![(-3, 13), (15, 25)]);
+        assert_eq!(coverage.count, 28);
+        assert_eq!(row_coverage(&sonars, &beacons, 100).intervals, vec![]);
+
+        assert_eq!(merge_intervals(vec![(5, 7), (0, 2), (3, 3), (6, 10), (12, 12)]), vec![(0, 3), (5, 10), (12, 12)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn locate_gap_test() -> Result<(), String> {
+        let (sonars, _) = parse_input(example_data())?;
+        assert_eq!(locate_gap(&sonars, ((0, 0), (20, 20))), Some((14, 11)));
+        assert_eq!(locate_gap(&sonars, ((0, 0), (13, 20))), None);
+
+        // the only free cell is a corner of the area, touched by a single sonar
+        let sonars = vec![Sonar { x: 10, y: 10, radius: 19 }];
+        assert_eq!(locate_gap(&sonars, ((0, 0), (10, 10))), Some((0, 0)));
+        assert_eq!(locate_gap(&sonars, ((0, 0), (0, 0))), Some((0, 0)));
+
+        // the only free cell lies on `x - y` lines only, no `x + y` line crosses it
+        let sonars = vec![
+            Sonar { x: 10, y: 2, radius: 7 },
+            Sonar { x: 2, y: 1, radius: 13 },
+            Sonar { x: 19, y: -2, radius: 8 },
+            Sonar { x: 6, y: 18, radius: 11 },
+            Sonar { x: 17, y: 15, radius: 12 },
+        ];
+        assert_eq!(locate_gap(&sonars, ((0, 0), (20, 20))), Some((9, 9)));
+
+        // dense random fields, any reported gap has to be free and none reported means the box is covered
+        let mut rng = Rng::new(40);
+        for _ in 0..3000 {
+            let sonars = (0..3 + rng.below(6))
+                .map(|_| Sonar { x: rng.below(30) - 5, y: rng.below(30) - 5, radius: 4 + rng.below(12) })
+                .collect::<Vec<_>>();
+            let (min, max) = ((rng.below(5), rng.below(5)), (5 + rng.below(16), 5 + rng.below(16)));
+            let free = (min.1..=max.1)
+                .flat_map(|y| (min.0..=max.0).map(move |x| (x, y)))
+                .filter(|point| sonars.iter().all(|sonar| !sonar.contains(point)))
+                .collect::<HashSet<_>>();
+            match locate_gap(&sonars, (min, max)) {
+                Some(point) => assert!(free.contains(&point), "{:?} {:?} {:?}", sonars, min, max),
+                None => assert!(free.is_empty(), "{:?} {:?} {:?}", sonars, min, max),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn example_test() -> Result<(), String> {
+        let config = SearchConfig::EXAMPLE;
+        let (sonars, beacons) = parse_input(example_data())?;
+        assert_eq!(task01(sonars.clone(), beacons, &config), 26);
+        assert_eq!(task02_rotated(&sonars, &config), Some(((14, 11), 56000011)));
+
+        let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+        assert_eq!(runtime.block_on(task02(sonars.clone(), config, 4))?, Some(56000011));
+        assert_eq!(runtime.block_on(search_range(Arc::new(sonars.clone()), config, 0, 20)), Some(56000011));
+        for workers in [1, 3, 8] {
+            assert_eq!(task02_threads(sonars.clone(), config, workers), Some(56000011));
+        }
+
+        let config = SearchConfig { multiplier: 10, ..config };
+        assert_eq!(task02_rotated(&sonars, &config), Some(((14, 11), 151)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn deterministic_test() {
+        // three separate gaps, the one on the boundary of the first sonar has to win every time
+        let sonars = vec![
+            Sonar { x: 2, y: 2, radius: 1 },
+            Sonar { x: 10, y: 10, radius: 1 },
+            Sonar { x: 20, y: 20, radius: 1 },
+        ];
+        let config = SearchConfig { min: (0, 0), max: (30, 30), ..SearchConfig::EXAMPLE };
+        let expected = BoundarySearch::new(sonars.clone(), config).search(0).map(|point| config.frequency(&point));
+        assert!(expected.is_some());
+        for _ in 0..20 {
+            assert_eq!(task02_threads(sonars.clone(), config, 3), expected);
+        }
+    }
+
+    #[test]
+    fn coverage_test() -> Result<(), String> {
+        let (sonars, _) = parse_input(example_data())?;
+        let coverage = sensor_coverage(&sonars, ((0, 0), (20, 20)));
+        assert_eq!(coverage.covered, 21 * 21 - 1);
+        assert_eq!(coverage.uncovered.iter().flat_map(|region| region.points()).collect::<Vec<_>>(), vec![(14, 11)]);
+
+        // random fields compared with the uncovered cells found by brute force
+        let mut rng = Rng::new(15);
+        for _ in 0..40 {
+            let sonars = (0..rng.below(5))
+                .map(|_| Sonar { x: rng.below(30) - 5, y: rng.below(30) - 5, radius: rng.below(8) })
+                .collect::<Vec<_>>();
+            let (min, max) = ((rng.below(5), rng.below(5)), (10 + rng.below(12), 10 + rng.below(12)));
+            let coverage = sensor_coverage(&sonars, (min, max));
+
+            let expected = (min.1..=max.1)
+                .flat_map(|y| (min.0..=max.0).map(move |x| (x, y)))
+                .filter(|point| sonars.iter().all(|sonar| !sonar.contains(point)))
+                .collect::<HashSet<_>>();
+            let mut found = coverage.uncovered.iter()
+                .flat_map(|region| region.points())
+                .collect::<Vec<_>>();
+            assert_eq!(found.len() as Size, coverage.uncovered.iter().map(|region| region.cells).sum::<Size>());
+            found.sort_unstable();
+            found.dedup();
+            assert_eq!(found.into_iter().collect::<HashSet<_>>(), expected);
+            assert_eq!(coverage.covered, (max.0 - min.0 + 1) * (max.1 - min.1 + 1) - expected.len() as Size);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_readings_test() -> Result<(), String> {
+        let readings = parse_readings(vec![
+            "  Sensor  at x = 2 , y=-18:closest beacon is at   x=-2, y=15 ".to_owned(),
+            "".to_owned(),
+        ])?;
+        assert_eq!(readings.len(), 1);
+        let (sonar, beacon) = &readings[0];
+        assert_eq!((sonar.x, sonar.y, sonar.radius), (2, -18, 37));
+        assert_eq!(*beacon, (-2, 15));
+
+        let error = |line: &str| parse_readings(vec![example_data()[0].clone(), line.to_owned()]).unwrap_err();
+        assert_eq!(error("Sensor at x=2, y=18"), "Line 2, column 20: expected ':', found end of line");
+        assert_eq!(error("Sensor at x=2, y=1a: closest beacon is at x=-2, y=15"),
+                   "Line 2, column 19: expected ':', found 'a'");
+        assert_eq!(error("Sensor at x=-, y=18: closest beacon is at x=-2, y=15"),
+                   "Line 2, column 13: expected number, found '-'");
+        assert_eq!(error("Sensor at x=2, y=18: farthest beacon is at x=-2, y=15"),
+                   "Line 2, column 22: expected 'closest', found 'f'");
+        assert_eq!(error("Sensor at x=2, y=18: closest beacon is at x=-2, y=15!"),
+                   "Line 2, column 53: expected end of line, found '!'");
+        assert_eq!(error("Sensoratx=2,y=18:closestbeaconisatx=-2,y=15"),
+                   "Line 2, column 7: expected whitespace, found 'a'");
+        assert_eq!(error("Sensor atx=2, y=18: closest beacon is at x=-2, y=15"),
+                   "Line 2, column 10: expected whitespace, found 'x'");
+        assert_eq!(error("Sensor at x=2, y=18: closest beaconis at x=-2, y=15"),
+                   "Line 2, column 36: expected whitespace, found 'i'");
+        assert_eq!(error("Sensor at x=99999999999999999999, y=18: closest beacon is at x=-2, y=15"),
+                   "Line 2, column 13: integer does not fit in 64 bits");
+        assert_eq!(parse_readings(example_data())?.len(), 14);
+
+        Ok(())
+    }
+
     #[test]
     fn task01_test() -> Result<(), String> {
         let (sonars, beacons) = parse_input(input_data())?;
-        println!("task01: {}", task01(sonars, beacons));
+        println!("task01: {}", task01(sonars, beacons, &SearchConfig::EXAMPLE));
 
         Ok(())
     }
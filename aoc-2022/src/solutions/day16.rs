@@ -1,110 +1,683 @@
-use std::cmp;
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::fmt;
+use std::fmt::Formatter;
+use std::collections::{HashMap, HashSet, VecDeque};
 use fancy_regex::Regex;
+use crate::parser::parse;
 
+/// Index of a valve in `Valves`, assigned in order of appearance in the scan.
+pub type ValveId = usize;
+
+/// Owned valve scan with interned ids, `rates` and `tunnels` are indexed by `ValveId`
+/// and `index` maps a name back to its id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Valves {
+    names: Vec<String>,
+    index: HashMap<String, ValveId>,
+    rates: Vec<u32>,
+    tunnels: Vec<Vec<ValveId>>,
+}
+
+impl Valves {
+    fn re() -> Regex {
+        Regex::new(r"^Valve (\w+) has flow rate=(\d+); tunnels? leads? to valves? (.+)$").unwrap()
+    }
+
+    fn parse_line(re: &Regex, line: &str) -> Result<(String, u32, Vec<String>), String> {
+        let cap = re.captures(line)
+            .map_err(|err| err.to_string())?.ok_or("Not a valve scan".to_string())?;
+        let rate = cap[2].parse::<u32>().map_err(|err| err.to_string())?;
+        let tunnels = cap[3].split(',').map(|id| id.trim().to_owned()).collect();
+        Ok((cap[1].to_owned(), rate, tunnels))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn id(&self, name: &str) -> Result<ValveId, String> {
+        self.index.get(name).copied().ok_or(format!("Valve {} not found", name))
+    }
+
+    pub fn name(&self, id: ValveId) -> &str {
+        &self.names[id]
+    }
+}
+
+/// Parses the scan, every tunnel has to lead to a scanned valve.
+pub fn parse_valves(input: &[String]) -> Result<Valves, String> {
+    let re = Valves::re();
+    let lines = input.iter().enumerate()
+        .map(|(idx, line)| Valves::parse_line(&re, line).map_err(|err| format!("Line {}: {}", idx + 1, err)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut index = HashMap::new();
+    for (idx, (name, _, _)) in lines.iter().enumerate() {
+        if index.insert(name.clone(), idx).is_some() {
+            return Err(format!("Line {}: valve {} is scanned twice", idx + 1, name));
+        }
+    }
+    let tunnels = lines.iter().enumerate()
+        .map(|(idx, (name, _, tunnels))| tunnels.iter()
+            .map(|target| index.get(target).copied()
+                .ok_or(format!("Line {}: tunnel from {} leads to unknown valve {}", idx + 1, name, target)))
+            .collect::<Result<Vec<_>, String>>())
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Valves {
+        rates: lines.iter().map(|(_, rate, _)| *rate).collect(),
+        names: lines.into_iter().map(|(name, _, _)| name).collect(),
+        index,
+        tunnels,
+    })
+}
+
+/// Parses the scan like [`parse_valves`], `start` has to be one of the scanned valves.
+pub fn parse_input(input: &[String], start: &str) -> Result<Valves, String> {
+    let valves = parse_valves(input)?;
+    valves.id(start).map_err(|_| format!("Start valve {} not found", start))?;
+    Ok(valves)
+}
+
+/// Valve graph reduced to the start valve and the valves worth opening. Every valve with a
+/// nonzero rate gets a bit index `0..rates.len()`, `dist` holds the shortest walk in minutes
+/// between any two of the kept valves, the start valve being the last row when it has no rate.
+/// The full graph is kept in `valves` to walk the actual tunnels of a plan.
+struct Network {
+    ids: Vec<String>,
+    rates: Vec<u32>,
+    dist: Vec<Vec<u32>>,
+    start: usize,
+    nodes: Vec<ValveId>,
+    valves: Valves,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Idle,
+    Move(String),
+    Open(String),
+}
+
+/// State during a single minute: valves open at its start, pressure they release in it,
+/// pressure released since the first minute and what every actor does.
 #[derive(Debug, Clone, PartialEq)]
-enum Status {
-    Closed,
-    Open,
+pub struct Minute {
+    pub minute: u32,
+    pub open: Vec<String>,
+    pub releasing: u32,
+    pub total: u32,
+    pub actions: Vec<Action>,
 }
 
-#[derive(Debug, Clone)]
-struct Valve<'a> {
-    id: &'a str,
-    rate: u16,
-    connected: Vec<&'a str>,
-    status: Status,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub score: u32,
+    pub minutes: Vec<Minute>,
 }
 
-impl<'a> Valve<'a> {
-    const MAX_TIME: u8 = 30;
+impl Network {
+    fn new(valves: &Valves, start: &str) -> Result<Network, String> {
+        let mut kept = (0..valves.len())
+            .filter(|&id| valves.rates[id] > 0)
+            .collect::<Vec<_>>();
+        if kept.len() > 64 {
+            return Err(format!("Too many valves with nonzero rate: {}", kept.len()));
+        }
+        let rates = kept.iter().map(|&id| valves.rates[id]).collect::<Vec<_>>();
+        let start_id = valves.id(start).map_err(|_| format!("Start valve {} not found", start))?;
+        let start = match kept.iter().position(|&id| id == start_id) {
+            Some(bit) => bit,
+            None => {
+                kept.push(start_id);
+                kept.len() - 1
+            }
+        };
 
-    fn re() -> Regex {
-        Regex::new(r"^Valve\s(.{2}).*rate=(\d*).*valves?\s(.*$)").unwrap()
+        let dist = kept.iter()
+            .map(|&from| {
+                let all = Self::bfs(valves, from);
+                kept.iter().map(|&to| all[to]).collect()
+            })
+            .collect();
+
+        Ok(Network {
+            ids: kept.iter().map(|&id| valves.names[id].clone()).collect(),
+            rates,
+            dist,
+            start,
+            nodes: kept,
+            valves: valves.clone(),
+        })
     }
 
-    fn open(&mut self) {
-        self.status = Status::Open;
+    /// Minutes needed to walk from `from` to every valve, `u32::MAX` for unreachable ones.
+    fn bfs(valves: &Valves, from: ValveId) -> Vec<u32> {
+        let mut dist = vec![u32::MAX; valves.len()];
+        let mut queue = VecDeque::from([from]);
+        dist[from] = 0;
+        while let Some(current) = queue.pop_front() {
+            for &next in valves.tunnels[current].iter() {
+                if dist[next] == u32::MAX {
+                    dist[next] = dist[current] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        dist
     }
 
-    fn close(&mut self) {
-        self.status = Status::Closed;
+    /// Most pressure that can still be released standing at `pos` with `time` minutes left
+    /// and the valves in `opened` already taken care of.
+    fn best(&self, pos: usize, time: u32, opened: u64, mem: &mut HashMap<(usize, u32, u64), u32>) -> u32 {
+        if let Some(&score) = mem.get(&(pos, time, opened)) {
+            return score;
+        }
+
+        let score = (0..self.rates.len())
+            .filter(|bit| opened & (1 << bit) == 0)
+            .filter_map(|bit| {
+                let left = time.checked_sub(self.dist[pos][bit].checked_add(1)?)?;
+                Some(self.rates[bit] * left + self.best(bit, left, opened | (1 << bit), mem))
+            })
+            .max()
+            .unwrap_or(0);
+        mem.insert((pos, time, opened), score);
+        score
     }
 
-    fn from_str(input: &'a str) -> Result<Self, String> {
-        let cap = Self::re().captures(input)
-            .map_err(|err| err.to_string())?.ok_or("Not found".to_string())?;
+    /// Best pressure a single actor releases for every set of valves it can open in `time`.
+    fn best_per_mask(&self, time: u32) -> HashMap<u64, u32> {
+        let mut best = HashMap::new();
+        self.visit(self.start, time, 0, 0, &mut best);
+        best
+    }
 
-        let rate = cap.get(2).ok_or("Not found rate".to_string())?.as_str().parse::<u16>()
-            .map_err(|err| err.to_string())?;
-        let connected = cap.get(3).ok_or("Not found connected".to_string())?.as_str()
-            .split(", ").map(|s| s.trim()).collect::<Vec<_>>();
-        Ok(Valve {
-            id: cap.get(1).ok_or("Not found id".to_string())?.as_str(),
-            rate,
-            connected,
-            status: Status::Closed,
-        })
+    fn visit(&self, pos: usize, time: u32, opened: u64, pressure: u32, best: &mut HashMap<u64, u32>) {
+        let entry = best.entry(opened).or_insert(0);
+        *entry = (*entry).max(pressure);
+        for bit in (0..self.rates.len()).filter(|bit| opened & (1 << bit) == 0) {
+            if let Some(left) = self.dist[pos][bit].checked_add(1).and_then(|cost| time.checked_sub(cost)) {
+                self.visit(bit, left, opened | (1 << bit), pressure + self.rates[bit] * left, best);
+            }
+        }
+    }
+
+    /// Actors working in parallel never open the same valve, so the best plan is the best
+    /// combination of single actor plans over disjoint valve sets. Returns the mask of every actor.
+    fn best_split(&self, time: u32, actors: usize) -> (u32, Vec<u64>) {
+        let mut plans = self.best_per_mask(time).into_iter().collect::<Vec<_>>();
+        plans.sort_unstable_by_key(|(_, score)| Reverse(*score));
+
+        let mut best = (0, vec![0; actors]);
+        Self::split(&plans, actors, 0, 0, &mut vec![], &mut best);
+        best
+    }
+
+    fn split(plans: &[(u64, u32)], actors: usize, used: u64, score: u32, chosen: &mut Vec<u64>,
+             best: &mut (u32, Vec<u64>)) {
+        if actors == 0 {
+            if score > best.0 {
+                *best = (score, chosen.clone());
+            }
+            return;
+        }
+        for (i, &(mask, value)) in plans.iter().enumerate() {
+            if score + value * actors as u32 <= best.0 {
+                break;
+            }
+            if mask & used == 0 {
+                chosen.push(mask);
+                Self::split(&plans[i..], actors - 1, used | mask, score + value, chosen, best);
+                chosen.pop();
+            }
+        }
+    }
+
+    fn full_mask(&self) -> u64 {
+        (0..self.rates.len()).fold(0, |mask, bit| mask | 1 << bit)
+    }
+
+    /// Order in which a single actor opens valves from `allowed` to get the best score.
+    fn route(&self, time: u32, allowed: u64) -> Vec<usize> {
+        let mut mem = HashMap::new();
+        let (mut pos, mut time, mut opened) = (self.start, time, self.full_mask() & !allowed);
+        let mut route = vec![];
+        loop {
+            let target = self.best(pos, time, opened, &mut mem);
+            let next = (0..self.rates.len())
+                .filter(|bit| opened & (1 << bit) == 0)
+                .filter_map(|bit| {
+                    let left = time.checked_sub(self.dist[pos][bit].checked_add(1)?)?;
+                    Some((bit, left))
+                })
+                .find(|&(bit, left)| target > 0
+                    && self.rates[bit] * left + self.best(bit, left, opened | (1 << bit), &mut mem) == target);
+            match next {
+                Some((bit, left)) => {
+                    route.push(bit);
+                    (pos, time, opened) = (bit, left, opened | (1 << bit));
+                }
+                None => return route,
+            }
+        }
+    }
+
+    /// Valves passed walking from node `from` to node `to`, without the first one.
+    fn walk(&self, from: usize, to: usize) -> Vec<ValveId> {
+        let (from, to) = (self.nodes[from], self.nodes[to]);
+        let mut parent = vec![usize::MAX; self.valves.len()];
+        let mut queue = VecDeque::from([from]);
+        parent[from] = from;
+        while let Some(current) = queue.pop_front() {
+            for &next in self.valves.tunnels[current].iter() {
+                if parent[next] == usize::MAX {
+                    parent[next] = current;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut path = vec![];
+        let mut current = to;
+        while current != from {
+            path.push(current);
+            current = parent[current];
+        }
+        path.reverse();
+        path
+    }
+
+    /// Minute by minute schedule of the given routes, one per actor.
+    fn schedule(&self, time: u32, routes: &[Vec<usize>]) -> Plan {
+        let actions = routes.iter()
+            .map(|route| {
+                let mut pos = self.start;
+                let mut actions = vec![];
+                for &bit in route {
+                    actions.extend(self.walk(pos, bit).into_iter()
+                        .map(|valve| Action::Move(self.valves.names[valve].clone())));
+                    actions.push(Action::Open(self.ids[bit].clone()));
+                    pos = bit;
+                }
+                actions.resize(time as usize, Action::Idle);
+                actions
+            })
+            .collect::<Vec<_>>();
+
+        let mut open: Vec<String> = vec![];
+        let mut total = 0;
+        let minutes = (0..time as usize)
+            .map(|idx| {
+                let releasing = open.iter()
+                    .map(|id| self.rates[self.ids.iter().position(|kept| kept == id).unwrap()])
+                    .sum::<u32>();
+                total += releasing;
+                let minute = Minute {
+                    minute: idx as u32 + 1,
+                    open: open.clone(),
+                    releasing,
+                    total,
+                    actions: actions.iter().map(|actions| actions[idx].clone()).collect(),
+                };
+                for action in minute.actions.iter() {
+                    if let Action::Open(id) = action {
+                        open.push(id.clone());
+                    }
+                }
+                open.sort();
+                minute
+            })
+            .collect();
+        Plan { score: total, minutes }
+    }
+
+    /// Best plan of all actors splitting the valves among them.
+    fn plan(&self, time: u32, actors: usize) -> Plan {
+        let routes = match actors {
+            1 => vec![self.route(time, self.full_mask())],
+            _ => self.best_split(time, actors).1.into_iter()
+                .map(|mask| self.route(time, mask))
+                .collect(),
+        };
+        self.schedule(time, &routes)
+    }
+}
+
+/// Valve the actors start at, minutes until the volcano erupts, number of actors and
+/// minutes it takes to train every actor besides you before anyone can move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValveConfig {
+    pub start: String,
+    pub minutes: u32,
+    pub actors: usize,
+    pub penalty: u32,
+}
+
+impl ValveConfig {
+    pub fn alone() -> Self {
+        ValveConfig { start: "AA".to_owned(), minutes: 30, actors: 1, penalty: 4 }
+    }
+
+    pub fn with_elephant() -> Self {
+        ValveConfig { actors: 2, ..Self::alone() }
+    }
+
+    /// Minutes every actor has left once training is over.
+    pub fn time(&self) -> Result<u32, String> {
+        if self.actors == 0 {
+            return Err("At least one actor is needed".to_owned());
+        }
+        self.penalty.checked_mul(self.actors as u32 - 1)
+            .and_then(|penalty| self.minutes.checked_sub(penalty))
+            .ok_or(format!("Training {} actors takes longer than {} minutes", self.actors - 1, self.minutes))
+    }
+}
+
+impl Default for ValveConfig {
+    fn default() -> Self {
+        ValveConfig::alone()
     }
 }
 
-fn parse_input<'a>(input: &'a Vec<String>) -> Result<Vec<Valve<'a>>, String> {
-    input.into_iter()
-        .map(move |line| Valve::<'a>::from_str(&line))
-        .collect::<Result<Vec<Valve<'a>>, String>>()
+fn solve(input: &Valves, config: &ValveConfig) -> Result<u32, String> {
+    let network = Network::new(input, &config.start)?;
+    let time = config.time()?;
+    Ok(match config.actors {
+        1 => network.best(network.start, time, 0, &mut HashMap::new()),
+        actors => network.best_split(time, actors).0,
+    })
 }
 
-fn explore(current: &str, map: &HashMap<&str, Valve>, time_left: u8, score: u32, opened: HashSet<&str>, mem: &mut HashMap<String, u32>) -> u32 {
-    if let Some(&score) = mem.get(format!("{}:{}:{:?}", current, time_left, opened).as_str()) {
-        return score;
+/// Best plan for `config`, with every actor's action in every minute.
+pub fn solve_plan(input: &Valves, config: &ValveConfig) -> Result<Plan, String> {
+    let network = Network::new(input, &config.start)?;
+    Ok(network.plan(config.time()?, config.actors))
+}
+
+fn task01(input: Valves) -> Result<u32, String> {
+    solve(&input, &ValveConfig::alone())
+}
+
+fn task02(input: Valves) -> Result<u32, String> {
+    solve(&input, &ValveConfig::with_elephant())
+}
+
+impl Plan {
+    /// Valves in the order each actor opens them.
+    pub fn openings(&self) -> Vec<Vec<String>> {
+        let actors = self.minutes.first().map(|minute| minute.actions.len()).unwrap_or(0);
+        (0..actors)
+            .map(|actor| self.minutes.iter()
+                .filter_map(|minute| match &minute.actions[actor] {
+                    Action::Open(id) => Some(id.clone()),
+                    _ => None,
+                })
+                .collect())
+            .collect()
     }
+}
+
+const DOT_COLORS: [&str; 4] = ["red", "blue", "darkgreen", "orange"];
+
+/// Graphviz DOT text of the tunnel graph. Valves with no flow are dashed, valves in `openings`
+/// are coloured per actor and numbered by the order they are opened in.
+pub fn dot(valves: &Valves, openings: &[Vec<String>]) -> String {
+    let order = openings.iter().enumerate()
+        .flat_map(|(actor, ids)| ids.iter().enumerate()
+            .map(move |(idx, id)| (id.as_str(), (actor, idx + 1))))
+        .collect::<HashMap<_, _>>();
 
-    if time_left == 0 || opened.len() == map.len() {
-        return score;
+    let mut lines = vec!["graph valves {".to_owned()];
+    for (name, rate) in valves.names.iter().zip(valves.rates.iter()) {
+        let mut attrs = vec![format!("label=\"{}\\n{}\"", name, rate)];
+        if *rate == 0 {
+            attrs.push("style=dashed".to_owned());
+        }
+        if let Some(&(actor, step)) = order.get(name.as_str()) {
+            attrs[0] = format!("label=\"{}\\n{}\\n#{}\"", name, rate, step);
+            attrs.push(format!("color={}", DOT_COLORS[actor % DOT_COLORS.len()]));
+            attrs.push("penwidth=2".to_owned());
+        }
+        lines.push(format!("    \"{}\" [{}];", name, attrs.join(", ")));
     }
 
-    let valve = map.get(current).unwrap();
-    let a = valve.connected.as_slice().iter()
-        .map(|neigh| {
-            explore(neigh, map,
-                    time_left - 1,
-                    score,
-                    opened.clone(), mem)
-        }).max().unwrap_or(score);
-    let b = if !opened.contains(current) {
-        let reward = valve.rate * (time_left - 1) as u16;
-        let mut opened = opened.clone();
-        opened.insert(current);
-        explore(current, map, time_left.checked_sub(1).unwrap_or(0), score + reward as u32, opened, mem)
-    } else { score };
-    let score = cmp::max(a, b);
-    mem.insert(format!("{}:{}:{:?}", current, time_left, opened), score);
-    score
+    let mut edges = HashSet::new();
+    for (id, tunnels) in valves.tunnels.iter().enumerate() {
+        for &other in tunnels.iter() {
+            let edge = (id.min(other), id.max(other));
+            if edges.insert(edge) {
+                lines.push(format!("    \"{}\" -- \"{}\";", valves.names[edge.0], valves.names[edge.1]));
+            }
+        }
+    }
+    lines.push("}".to_owned());
+    lines.join("\n") + "\n"
+}
+
+/// Prints the DOT graph of the input, overlaid with the best plan for `config` when given.
+pub fn wrapper_dot(path: &str, config: Option<&ValveConfig>) -> Result<(), String> {
+    let input = parse(path);
+    let (valves, openings) = match config {
+        None => (parse_valves(&input)?, vec![]),
+        Some(config) => {
+            let valves = parse_input(&input, &config.start)?;
+            let openings = solve_plan(&valves, config)?.openings();
+            (valves, openings)
+        }
+    };
+    print!("{}", dot(&valves, &openings));
+    Ok(())
+}
+
+pub fn wrapper_solve(path: &str, config: &ValveConfig) -> Result<u32, String> {
+    solve(&parse_input(&parse(path), &config.start)?, config)
 }
 
-fn task01(input: Vec<Valve>) -> u32 {
-    let opened = input.iter().filter(|valve| valve.rate == 0)
-        .map(|valve| valve.id).collect::<HashSet<&str>>();
-    let map = input.into_iter()
-        .map(|valve| (valve.id, valve))
-        .collect::<HashMap<&str, Valve>>();
-    explore("AA", &map, 30, 0, opened, &mut HashMap::new())
+/// Prints the minute by minute narrative of the best plan.
+pub fn wrapper_explain(path: &str, config: &ValveConfig) -> Result<(), String> {
+    print!("{}", solve_plan(&parse_input(&parse(path), &config.start)?, config)?);
+    Ok(())
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let actors = self.minutes.first().map(|minute| minute.actions.len()).unwrap_or(0);
+        for (idx, minute) in self.minutes.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "== Minute {} ==", minute.minute)?;
+            match minute.open.as_slice() {
+                [] => writeln!(f, "No valves are open.")?,
+                [id] => writeln!(f, "Valve {} is open, releasing {} pressure.", id, minute.releasing)?,
+                [a, b] => writeln!(f, "Valves {} and {} are open, releasing {} pressure.", a, b, minute.releasing)?,
+                [rest @ .., last] => writeln!(f, "Valves {}, and {} are open, releasing {} pressure.",
+                                              rest.join(", "), last, minute.releasing)?,
+            }
+            for (actor, action) in minute.actions.iter().enumerate() {
+                let (who, verb) = match (actor, actors) {
+                    (0, _) => ("You".to_owned(), ""),
+                    (_, 2) => ("The elephant".to_owned(), "s"),
+                    (actor, _) => (format!("Elephant {}", actor), "s"),
+                };
+                match action {
+                    Action::Move(id) => writeln!(f, "{} move{} to valve {}.", who, verb, id)?,
+                    Action::Open(id) => writeln!(f, "{} open{} valve {}.", who, verb, id)?,
+                    Action::Idle => {}
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::parse;
 
     fn input_data() -> Vec<String> {
         parse("resources/day16.in")
     }
 
+    fn example_data() -> Vec<String> {
+        [
+            "Valve AA has flow rate=0; tunnels lead to valves DD, II, BB",
+            "Valve BB has flow rate=13; tunnels lead to valves CC, AA",
+            "Valve CC has flow rate=2; tunnels lead to valves DD, BB",
+            "Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE",
+            "Valve EE has flow rate=3; tunnels lead to valves FF, DD",
+            "Valve FF has flow rate=0; tunnels lead to valves EE, GG",
+            "Valve GG has flow rate=0; tunnels lead to valves FF, HH",
+            "Valve HH has flow rate=22; tunnel leads to valve GG",
+            "Valve II has flow rate=0; tunnels lead to valves AA, JJ",
+            "Valve JJ has flow rate=21; tunnel leads to valve II",
+        ].into_iter().map(|line| line.to_owned()).collect()
+    }
+
+    #[test]
+    fn parse_input_test() -> Result<(), String> {
+        let input_data = example_data();
+        let valves = parse_input(&input_data, "AA")?;
+        assert_eq!(valves.len(), 10);
+        assert_eq!(valves.id("DD")?, 3);
+        assert_eq!(valves.name(9), "JJ");
+        assert_eq!(valves.rates[3], 20);
+        assert_eq!(valves.tunnels[0], vec![3, 8, 1]);
+        assert_eq!(valves.tunnels[7], vec![6]);
+
+        assert_eq!(parse_input(&input_data, "ZZ"), Err("Start valve ZZ not found".to_owned()));
+        let mut broken = input_data.clone();
+        broken[7] = "Valve HH has flow rate=22; tunnel leads to valve XX".to_owned();
+        assert_eq!(parse_input(&broken, "AA"), Err("Line 8: tunnel from HH leads to unknown valve XX".to_owned()));
+        broken[7] = "Valve HH has flow rate=-2; tunnel leads to valve GG".to_owned();
+        assert_eq!(parse_input(&broken, "AA"), Err("Line 8: Not a valve scan".to_owned()));
+        broken[7] = "Valve GG has flow rate=22; tunnel leads to valve GG".to_owned();
+        assert_eq!(parse_input(&broken, "AA"), Err("Line 8: valve GG is scanned twice".to_owned()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn network_test() -> Result<(), String> {
+        let input_data = example_data();
+        let valves = parse_input(&input_data, "AA")?;
+        let network = Network::new(&valves, "AA")?;
+        assert_eq!(network.rates, vec![13, 2, 20, 3, 22, 21]);
+        assert_eq!(network.ids[network.start], "AA");
+        assert_eq!(network.dist[network.start], vec![1, 2, 1, 2, 5, 2, 0]);
+        assert_eq!(network.dist[4][5], 7);
+        assert!(Network::new(&valves, "ZZ").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_test() -> Result<(), String> {
+        let input_data = example_data();
+        let plan = solve_plan(&parse_input(&input_data, "AA")?, &ValveConfig::alone())?;
+        assert_eq!(plan.score, 1651);
+        assert_eq!(plan.minutes.len(), 30);
+        let text = plan.to_string();
+        assert!(text.starts_with("== Minute 1 ==\nNo valves are open.\nYou move to valve DD.\n\n\
+                                  == Minute 2 ==\nNo valves are open.\nYou open valve DD.\n\n\
+                                  == Minute 3 ==\nValve DD is open, releasing 20 pressure.\nYou move to valve CC.\n"));
+        assert!(text.contains("== Minute 30 ==\nValves BB, CC, DD, EE, HH, and JJ are open, releasing 81 pressure.\n"));
+
+        let plan = solve_plan(&parse_input(&input_data, "AA")?, &ValveConfig::with_elephant())?;
+        assert_eq!(plan.score, 1707);
+        assert_eq!(plan.minutes[25].total, 1707);
+        assert_eq!(plan.minutes[25].open.len(), 6);
+        assert!(plan.to_string().contains("The elephant open"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dot_test() -> Result<(), String> {
+        let input_data = example_data();
+        let valves = parse_input(&input_data, "AA")?;
+
+        let text = dot(&valves, &[]);
+        assert!(text.starts_with("graph valves {\n    \"AA\" [label=\"AA\\n0\", style=dashed];\n    \"BB\" [label=\"BB\\n13\"];\n"));
+        assert!(text.ends_with("    \"AA\" -- \"DD\";\n    \"AA\" -- \"II\";\n    \"AA\" -- \"BB\";\n    \"BB\" -- \"CC\";\n    \
+                                \"CC\" -- \"DD\";\n    \"DD\" -- \"EE\";\n    \"EE\" -- \"FF\";\n    \"FF\" -- \"GG\";\n    \
+                                \"GG\" -- \"HH\";\n    \"II\" -- \"JJ\";\n}\n"));
+
+        let openings = solve_plan(&valves, &ValveConfig::with_elephant())?.openings();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings.iter().map(|ids| ids.len()).sum::<usize>(), 6);
+        let text = dot(&valves, &openings);
+        assert!(text.contains(&format!("    \"{}\" [label=\"{}\\n", openings[0][0], openings[0][0])));
+        assert!(text.contains("\\n#1\", color=red, penwidth=2];"));
+        assert!(text.contains("\\n#1\", color=blue, penwidth=2];"));
+
+        // names starting with a digit are no valid bare DOT ids
+        let input_data = vec![
+            "Valve 1A has flow rate=0; tunnel leads to valve 2B".to_owned(),
+            "Valve 2B has flow rate=5; tunnel leads to valve 1A".to_owned(),
+        ];
+        let text = dot(&parse_valves(&input_data)?, &[]);
+        assert!(text.contains("    \"1A\" [label=\"1A\\n0\", style=dashed];\n"));
+        assert!(text.ends_with("    \"1A\" -- \"2B\";\n}\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_test() -> Result<(), String> {
+        let input_data = example_data();
+        let valves = parse_input(&input_data, "AA")?;
+        assert_eq!(ValveConfig::with_elephant().time()?, 26);
+
+        let alone_26 = ValveConfig { minutes: 26, ..ValveConfig::alone() };
+        assert!(solve(&valves, &alone_26)? < 1707);
+        let no_training = ValveConfig { penalty: 0, ..ValveConfig::with_elephant() };
+        assert!(solve(&valves, &no_training)? > 1707);
+
+        let three = ValveConfig { actors: 3, ..ValveConfig::alone() };
+        assert_eq!(three.time()?, 22);
+        let plan = solve_plan(&valves, &three)?;
+        assert_eq!(plan.score, solve(&valves, &three)?);
+        assert_eq!(plan.openings().len(), 3);
+
+        let from_jj = ValveConfig { start: "JJ".to_owned(), ..ValveConfig::alone() };
+        assert!(solve(&valves, &from_jj)? > 0);
+
+        assert!(solve(&valves, &ValveConfig { actors: 0, ..ValveConfig::alone() }).is_err());
+        assert!(solve(&valves, &ValveConfig { actors: 9, ..ValveConfig::alone() }).is_err());
+        assert!(solve(&valves, &ValveConfig { start: "ZZ".to_owned(), ..ValveConfig::alone() }).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn example_test() -> Result<(), String> {
+        let input_data = example_data();
+        assert_eq!(task01(parse_input(&input_data, "AA")?)?, 1651);
+        assert_eq!(task02(parse_input(&input_data, "AA")?)?, 1707);
+
+        Ok(())
+    }
+
     #[test]
     fn task01_test() -> Result<(), String> {
         let input_data = input_data();
-        let valves = parse_input(&input_data)?;
-        println!("task01: {}", task01(valves));
+        let valves = parse_input(&input_data, "AA")?;
+        println!("task01: {}", task01(valves)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn task02_test() -> Result<(), String> {
+        let input_data = input_data();
+        let valves = parse_input(&input_data, "AA")?;
+        println!("task02: {}", task02(valves)?);
 
         Ok(())
     }
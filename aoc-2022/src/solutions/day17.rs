@@ -1,237 +1,308 @@
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 use std::fmt::Formatter;
+use std::str::FromStr;
 use crate::parser::parse;
 
 type Point = (usize, usize);
+type Row = u64;
 
+/// Shape index of the next rock, index of the next jet and the top rows of the tower, which
+/// decide what happens next as long as no rock falls deeper than those rows.
+type Fingerprint = (usize, usize, Vec<Row>);
+
+/// Rows from the top of the tower a fingerprint covers.
+const FINGERPRINT_ROWS: usize = 32;
+
+/// Rocks simulated at most while looking for a cycle.
+const CYCLE_LIMIT: u64 = 100_000;
+
+/// Rows a tower keeps at most, even when the rows below could still be reached.
+const MAX_ROWS: usize = 1024;
+
+/// Rocks of the puzzle in the order they fall.
+const ROCKS: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##";
+
+/// Rock drawn as ASCII art, `#` for rock and `.` for air, top row first, an indent of spaces
+/// and tabs common to all lines is ignored. Stored as one bitmask per row from the bottom up, bit `x` set for an
+/// occupied column `x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shape {
+    rows: Vec<Row>,
+    width: usize,
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let blank = [' ', '\t'];
+        let lines = s.lines()
+            .map(|line| line.trim_end_matches(blank))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let indent = lines.iter()
+            .map(|line| line.len() - line.trim_start_matches(blank).len())
+            .min().unwrap_or(0);
+
+        let mut cells = vec![];
+        for line in lines {
+            let row = line[indent..].chars().enumerate()
+                .map(|(x, ch)| match ch {
+                    '#' => Ok(Some(x)),
+                    '.' => Ok(None),
+                    ch => Err(format!("Unexpected character '{}'", ch)),
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            cells.push(row.into_iter().flatten().collect::<Vec<_>>());
+        }
+        while cells.last().is_some_and(|row| row.is_empty()) {
+            cells.pop();
+        }
+        while cells.first().is_some_and(|row| row.is_empty()) {
+            cells.remove(0);
+        }
+
+        let min = cells.iter().flatten().min().ok_or("Shape has no rock")?;
+        let max = cells.iter().flatten().max().ok_or("Shape has no rock")?;
+        let width = max - min + 1;
+        if width > Row::BITS as usize {
+            return Err(format!("Shape is {} wide, at most {} is supported", width, Row::BITS));
+        }
+        let rows = cells.iter().rev()
+            .map(|row| row.iter().fold(0, |mask, x| mask | 1 << (x - min)))
+            .collect();
+        Ok(Shape { rows, width })
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_rows(f, self.rows.iter().rev(), self.width)
+    }
+}
+
+/// Shapes separated by blank lines.
+pub fn parse_shapes(input: &str) -> Result<Vec<Shape>, String> {
+    let mut blocks = vec![String::new()];
+    for line in input.lines() {
+        match (line.trim().is_empty(), blocks.last_mut()) {
+            (true, Some(block)) if !block.is_empty() => blocks.push(String::new()),
+            (false, Some(block)) => {
+                block.push_str(line);
+                block.push('\n');
+            }
+            _ => {}
+        }
+    }
+    blocks.into_iter()
+        .filter(|block| !block.is_empty())
+        .enumerate()
+        .map(|(idx, block)| block.parse().map_err(|err| format!("Shape {}: {}", idx + 1, err)))
+        .collect()
+}
+
+/// Chamber `width`, rocks spawning with their left edge `spawn_x` from the left wall and
+/// their bottom `spawn_gap` rows above the top of the tower, falling in the order of `shapes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChamberConfig {
+    pub width: usize,
+    pub spawn_x: usize,
+    pub spawn_gap: usize,
+    pub shapes: Vec<Shape>,
+}
+
+impl ChamberConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.width == 0 || self.width > Row::BITS as usize {
+            return Err(format!("Chamber width {} is not in 1..={}", self.width, Row::BITS));
+        }
+        if self.shapes.is_empty() {
+            return Err("No rock shapes".to_owned());
+        }
+        match self.shapes.iter().position(|shape| self.spawn_x + shape.width > self.width) {
+            Some(idx) => Err(format!("Shape {} does not fit the chamber at x = {}", idx + 1, self.spawn_x)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for ChamberConfig {
+    fn default() -> Self {
+        ChamberConfig {
+            width: 7,
+            spawn_x: 2,
+            spawn_gap: 3,
+            shapes: parse_shapes(ROCKS).unwrap(),
+        }
+    }
+}
+
+/// Rock as shifted row bitmasks of its shape with its bottom row at `y`.
+#[derive(Clone, PartialEq)]
 struct Block {
-    _points: Box<Vec<Point>>,
-    _pos: Point,
+    rows: Vec<Row>,
+    y: usize,
 }
 
+/// Settled rocks as one bitmask per row. Rows no falling rock can reach any more are dropped
+/// from the front of `rows`, `floor` counts them. A surface that never closes, like a column no
+/// jet pushes a rock into, keeps every row reachable, so beyond `depth` rows the lowest ones are
+/// dropped anyway and `cut` marks that the rows below `floor` are no longer known to be sealed.
 struct Tower {
-    _lines: [Vec<bool>; 7],
-    _ground_offset: usize,
-    _generator: Box<dyn Iterator<Item=Block>>,
-    _moves: Box<dyn Iterator<Item=Move>>,
+    rows: VecDeque<Row>,
+    floor: usize,
+    depth: usize,
+    cut: bool,
+    rocks: usize,
+    moves: Vec<Move>,
+    jet: usize,
+    config: ChamberConfig,
+    full: Row,
 }
 
 impl Tower {
-    const MIN_OFFSET: usize = 3;
-
-    fn fall(&mut self) {
-        // println!("{:?}", self);
-        // preparation
-        self.level();
-        let real_height = self.real_height();
-        let mut block = self._generator.next().unwrap().move_to(&(2, real_height));
-        self.add_levels(block.height());
+    fn new(moves: Vec<Move>, config: ChamberConfig) -> Result<Self, String> {
+        config.validate()?;
+        if moves.is_empty() {
+            return Err("No jets".to_owned());
+        }
+        Ok(Tower {
+            rows: VecDeque::new(),
+            floor: 0,
+            depth: MAX_ROWS,
+            cut: false,
+            rocks: 0,
+            moves,
+            jet: 0,
+            full: Row::MAX >> (Row::BITS as usize - config.width),
+            config,
+        })
+    }
 
-        // println!("block: {:?}", block._points);
-        // println!("real_height: {}", self.real_height());
+    /// Drops the next rock, failing when it would have to fall below the dropped rows.
+    fn fall(&mut self) -> Result<(), String> {
+        let shape = &self.config.shapes[self.rocks % self.config.shapes.len()];
+        self.rocks += 1;
+        let mut block = Block::of(shape, (self.config.spawn_x, self.height() + self.config.spawn_gap));
 
         loop {
-            let next_move = self._moves.next().unwrap();
-            // println!("next_move: {:?}", next_move);
-            let next_block = block.make_move(&next_move);
+            let next_move = self.moves[self.jet];
+            self.jet = (self.jet + 1) % self.moves.len();
+            let next_block = block.make_move(&next_move, self.config.width);
             if !self.is_colliding(&next_block) {
                 block = next_block;
             }
-            // println!("block;;;: {:?}", block._points);
 
-            let next_block = block.make_move(&Move::Down);
-            if self.is_colliding(&next_block) || block._points.iter().any(|(_, y)| *y == 0) {
+            let next_block = block.make_move(&Move::Down, self.config.width);
+            if self.cut && next_block.y < self.floor {
+                return Err(format!("Rock {} falls deeper than the {} kept rows", self.rocks, self.depth));
+            }
+            if next_block == block || self.is_colliding(&next_block) {
                 self.set_points(&block);
-                if self.cycle_detection() {
-                    println!("cycle_detection: {:?}", self.height());
-                }
-                self.optimise(&block);
-                break;
+                self.discard_unreachable();
+                return Ok(());
             }
             block = next_block;
         }
     }
 
-    fn new(start: Point, moves: Vec<Move>) -> Self {
-        let mut lines: [Vec<bool>; 7] = Default::default();
-        lines.iter_mut()
-            .for_each(|line|
-                line.append(Vec::from([false; 3].as_ref()).as_mut())
-            );
-        Tower {
-            _lines: lines,
-            _ground_offset: 0,
-            _generator: Box::new(gen_blocks(start)),
-            _moves: Box::new(moves.into_iter().cycle()),
-        }
-    }
-
-    fn level(&mut self) {
-        let height = self.height();
-        let expected_height = height + Self::MIN_OFFSET;
-        // println!("expected_height: {}, {}", expected_height, height);
-        self._lines.iter_mut()
-            .for_each(|line| {
-                match line.len() {
-                    len if len < expected_height => {
-                        let diff = expected_height - len;
-                        for _ in 0..diff {
-                            line.push(false);
-                        }
-                    }
-                    len if len > expected_height => {
-                        let diff = len - expected_height;
-                        for _ in 0..diff {
-                            line.pop();
-                        }
-                    }
-                    _ => {}
-                }
-            })
-    }
-
-    fn add_levels(&mut self, levels: usize) {
-        self._lines.iter_mut()
-            .for_each(|line| {
-                for _ in 0..levels {
-                    line.push(false);
-                }
-            })
-    }
-
     fn height(&self) -> usize {
-        self._lines.iter()
-            .map(|line| self.line_height(line))
-            .max().unwrap()
+        self.floor + self.rows.len()
     }
 
-    fn real_height(&self) -> usize {
-        self._lines.iter()
-            .map(|line| line.len())
-            .max().unwrap() + self._ground_offset
-    }
-
-    fn line_height(&self, vec: &Vec<bool>) -> usize {
-        (vec.iter()
-            .rposition(|&b| b).map(|val| val as isize)
-            .unwrap_or(-1) + 1) as usize + self._ground_offset
+    /// Row `y` of the tower, rows below the kept ones are treated as full.
+    fn row(&self, y: usize) -> Row {
+        match y.checked_sub(self.floor) {
+            None => self.full,
+            Some(idx) => self.rows.get(idx).copied().unwrap_or(0),
+        }
     }
-
     fn is_colliding(&self, block: &Block) -> bool {
-        block._points.iter()
-            .any(|(x, y)|
-                *self._lines
-                    .get(*x).unwrap_or(&vec![])
-                    .get(*y).unwrap_or(&true)
-            )
+        block.rows.iter().enumerate()
+            .any(|(dy, row)| self.row(block.y + dy) & row != 0)
     }
 
     fn set_points(&mut self, block: &Block) {
-        // println!("set_points: {:?}", block._points);
-        block._points.iter()
-            .for_each(|(x, y)| {
-                self._lines[*x][*y] = true;
-            });
-    }
-
-    fn optimise(&mut self, last_block: &Block) {
-        for y in last_block._points.iter().map(|(_, y)| *y) {
-            let is_filled = (0..7).into_iter()
-                .map(|x| self._lines[x][y])
-                .all(|val| val);
-            if is_filled {
-                self._lines.iter_mut()
-                    .for_each(|line| {
-                        line.drain(0..y);
-                    });
-                println!("optimising: {}", y);
-                self._ground_offset += y;
-                return;
+        for (dy, row) in block.rows.iter().enumerate() {
+            let idx = block.y + dy - self.floor;
+            if idx >= self.rows.len() {
+                self.rows.resize(idx + 1, 0);
             }
+            self.rows[idx] |= row;
         }
     }
 
-    fn cycle_detection(&self) -> bool {
-        let height = self.height();
-        if height % 2 != 0 {
-            return false;
-        }
-        if !self._lines.iter()
-            .all(|line| line.get(height / 2 - 1) == line.get(height - 1)) {
-            return false;
+    /// Flood fills the free cells reachable from above the tower, every row under the one
+    /// below the lowest reachable cell can never be touched again. Rows beyond `depth` are
+    /// dropped as well.
+    fn discard_unreachable(&mut self) {
+        let mut reachable = self.full;
+        let mut lowest = self.height();
+        let mut sealed = false;
+        for y in (self.floor..self.height()).rev() {
+            let free = !self.row(y) & self.full;
+            let mut cells = reachable & free;
+            loop {
+                let spread = (cells | cells << 1 | cells >> 1) & free;
+                if spread == cells {
+                    break;
+                }
+                cells = spread;
+            }
+            if cells == 0 {
+                sealed = true;
+                break;
+            }
+            reachable = cells;
+            lowest = y;
         }
+        let discard = lowest.saturating_sub(1).saturating_sub(self.floor);
+        let excess = (self.rows.len() - discard).saturating_sub(self.depth);
+        self.cut = excess > 0 || (self.cut && !sealed);
+        self.rows.drain(..discard + excess);
+        self.floor += discard + excess;
+    }
 
-        self._lines.iter()
-            .map(|line|
-                line[0..(height/2)].iter()
-                    .zip(line.iter().skip(height / 2))
-                    .all(|(a, b)| a == b)
-            ).all(|val| val)
+    fn fingerprint(&self) -> Fingerprint {
+        let top = self.rows.len().saturating_sub(FINGERPRINT_ROWS);
+        (self.rocks % self.config.shapes.len(), self.jet, self.rows.range(top..).copied().collect())
     }
 }
 
-impl fmt::Debug for Block {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let mut min_x = usize::MAX;
-        let mut min_y = usize::MAX;
-        let mut max_x = usize::MIN;
-        let mut max_y = usize::MIN;
-        for (x, y) in self._points.iter() {
-            if *x < min_x {
-                min_x = *x;
-            }
-            if *y < min_y {
-                min_y = *y;
-            }
-            if *x > max_x {
-                max_x = *x;
-            }
-            if *y > max_y {
-                max_y = *y;
-            }
-        }
-        let mut result = String::new();
-        for y in min_y..=max_y {
-            for x in min_x..=max_x {
-                if self._points.contains(&(x, y)) {
-                    result.push('#');
-                } else {
-                    result.push('.');
-                }
-            }
-            result.push('\n');
-        }
-        write!(f, "{}", result)
+fn fmt_rows<'a>(f: &mut Formatter<'_>, rows: impl Iterator<Item=&'a Row>, width: usize) -> fmt::Result {
+    for row in rows {
+        let line = (0..width)
+            .map(|x| if row & (1 << x) != 0 { '#' } else { '.' })
+            .collect::<String>();
+        writeln!(f, "{}", line)?;
     }
+    Ok(())
 }
 
 impl fmt::Debug for Tower {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let mut result = String::new();
-        let height = self.real_height();
-        for y in (0..height).rev() {
-            for x in 0..7 {
-                if self._lines[x][y] {
-                    result.push('#');
-                } else {
-                    result.push('.');
-                }
-            }
-            result.push('\n');
-        }
-        write!(f, "{}", result)
+        fmt_rows(f, self.rows.iter().rev(), self.config.width)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Type {
-    A,
-    B,
-    C,
-    D,
-    E,
-}
-
 #[derive(Clone, Copy)]
 enum Move {
     Left,
@@ -259,106 +330,73 @@ impl fmt::Debug for Move {
     }
 }
 
-impl Type {
-    fn all() -> Vec<Type> {
-        vec![Type::A, Type::B, Type::C, Type::D, Type::E]
+impl Block {
+    fn of(shape: &Shape, (x0, y0): Point) -> Self {
+        Block { rows: shape.rows.iter().map(|row| row << x0).collect(), y: y0 }
     }
-}
 
-fn gen_blocks(start: Point) -> impl Iterator<Item=Block> {
-    Type::all().into_iter().cycle()
-        .map(move |tp| Block::of(&tp, start))
-}
-
-impl Block {
-    fn of(tp: &Type, (x0, y0): Point) -> Self {
-        let points = match tp {
-            Type::A => vec![(0, 0), (1, 0), (2, 0), (3, 0)],
-            Type::B => vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],
-            Type::C => vec![(2, 2), (2, 1), (0, 0), (1, 0), (2, 0)],
-            Type::D => vec![(0, 0), (0, 1), (0, 2), (0, 3)],
-            Type::E => vec![(0, 0), (1, 0), (0, 1), (1, 1)],
+    /// The same block when the move would push it into a wall or through the ground.
+    fn make_move(&self, mv: &Move, width: usize) -> Self {
+        let edge = match mv {
+            Move::Left => 1,
+            Move::Right => 1 << (width - 1),
+            Move::Down => 0,
         };
-        Block {
-            _points: Box::new(points.into_iter().map(|(x, y)| (x + x0, y + y0)).collect()),
-            _pos: (x0, y0),
+        if self.rows.iter().any(|row| row & edge != 0) {
+            return self.clone();
+        }
+        match mv {
+            Move::Left => Block { rows: self.rows.iter().map(|row| row >> 1).collect(), y: self.y },
+            Move::Right => Block { rows: self.rows.iter().map(|row| row << 1).collect(), y: self.y },
+            Move::Down => Block { rows: self.rows.clone(), y: self.y.checked_sub(1).unwrap_or(self.y) },
         }
     }
 
-    fn move_to(&self, point: &Point) -> Self {
-        let (x0, y0) = self._pos;
-        let (x1, y1) = point;
-        let points = self._points.iter()
-            .map(|(x, y)| (x + x1 - x0, y + y1 - y0))
-            .collect::<Vec<_>>();
-        Block {
-            _points: Box::new(points),
-            _pos: *point,
-        }
+    fn height(&self) -> usize {
+        self.rows.len()
     }
+}
 
-    fn make_move(&self, mv: &Move) -> Self {
-        let (x0, y0) = self._pos;
-        let points = self._points.iter()
-            .map(|(x, y)| match mv {
-                Move::Left => (x.checked_sub(1), Some(*y)),
-                Move::Right => (match x + 1 {
-                    val if val >= 7 => None,
-                    val => Some(val)
-                }, Some(*y)),
-                Move::Down => (Some(*x), y.checked_sub(1)),
-            })
-            .map(|(x, y)| match (x, y) {
-                (Some(x), Some(y)) => Some((x, y)),
-                _ => None,
-            })
-            .collect::<Option<Vec<_>>>();
-        if points.is_none() {
-            let points = self._points.iter()
-                .map(|(x, y)| (*x, *y))
-                .collect::<Vec<_>>();
-            // println!("points: {:?}", points);
-            return Block {
-                _points: Box::new(points),
-                _pos: (x0, y0),
-            };
+/// Height of the tower after `rocks` rocks. A repeated fingerprint suggests a cycle, which is
+/// trusted once the heights of the next period grow exactly like the ones of the first, and the
+/// height of the remaining rocks is extrapolated from it.
+fn tower_height(input: Vec<Move>, config: ChamberConfig, rocks: u64) -> Result<u64, String> {
+    let mut tower = Tower::new(input, config)?;
+    let mut seen = HashMap::new();
+    let mut heights = vec![0u64];
+    let mut candidate: Option<(u64, u64)> = None;
+    for rock in 1..=rocks {
+        tower.fall()?;
+        heights.push(tower.height() as u64);
+        let height = |rock: u64| heights[rock as usize];
+
+        if let Some((start, period)) = candidate.filter(|(start, period)| rock == start + 2 * period) {
+            candidate = None;
+            let repeats = (1..=period)
+                .all(|i| height(start + i) - height(start) == height(start + period + i) - height(start + period));
+            if repeats {
+                let (cycles, rest) = ((rocks - rock) / period, (rocks - rock) % period);
+                let growth = height(rock) - height(rock - period);
+                let tail = height(rock - period + rest) - height(rock - period);
+                return Ok(height(rock) + cycles * growth + tail);
+            }
         }
-        Block {
-            _points: Box::new(points.unwrap()),
-            _pos: match mv {
-                Move::Left => (x0 - 1, y0),
-                Move::Right => (x0 + 1, y0),
-                Move::Down => (x0, y0 - 1),
-            },
+        if let Some(prev) = seen.insert(tower.fingerprint(), rock) {
+            candidate = candidate.or(Some((prev, rock - prev)));
+        }
+        if rock >= CYCLE_LIMIT && rock < rocks && candidate.is_none() {
+            return Err(format!("No cycle found within {} rocks", rock));
         }
     }
-
-    fn height(&self) -> usize {
-        let max = self._points.iter().map(|(_, y)| y).max().unwrap();
-        let min = self._points.iter().map(|(_, y)| y).min().unwrap();
-        // println!("{:?}", self);
-        // println!("max: {}, min: {}", max, min);
-        max - min + 1
-    }
+    Ok(heights[rocks as usize])
 }
 
-fn task01(input: Vec<Move>) -> u64 {
-    let mut tower = Tower::new((0, 0), input);
-    for _ in 0i64..2022 {
-        tower.fall();
-    }
-    tower.height() as u64
+fn task01(input: Vec<Move>) -> Result<u64, String> {
+    tower_height(input, ChamberConfig::default(), 2022)
 }
 
-fn task02(input: Vec<Move>) -> u64 {
-    let mut tower = Tower::new((0, 0), input);
-    for i in 0i64..1000000000000 {
-        if i % 1000000 == 0 {
-            println!("i: {}, real_height: {}", i, tower.real_height());
-        }
-        tower.fall();
-    }
-    tower.height() as u64
+fn task02(input: Vec<Move>) -> Result<u64, String> {
+    tower_height(input, ChamberConfig::default(), 1000000000000)
 }
 
 fn parse_input(input: &Vec<String>) -> Result<Vec<Move>, String> {
@@ -373,26 +411,198 @@ fn input_data() -> Vec<String> {
 pub fn wrapper_task02() {
     let input_data = input_data();
     let valves = parse_input(&input_data).expect("Error parse input");
-    println!("task02: {}", task02(valves));
+    println!("task02: {}", task02(valves).expect("Error solve"));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::solutions::rng::Rng;
 
     #[test]
     fn test_blocks() {
-        let blocks = gen_blocks((0, 0)).take(5).collect::<Vec<Block>>();
-        for i in blocks {
-            println!("{:?}", i);
+        let shapes = ChamberConfig::default().shapes;
+        assert_eq!(shapes.len(), 5);
+        assert_eq!(shapes.iter().map(|shape| shape.to_string()).collect::<Vec<_>>().join("\n"), ROCKS.to_owned() + "\n");
+        assert_eq!(shapes[2].rows, vec![0b111, 0b100, 0b100]);
+        assert_eq!(Block::of(&shapes[0], (2, 5)).rows, vec![0b111100]);
+    }
+
+    #[test]
+    fn shape_test() {
+        assert_eq!("..#.\n.##.\n....".parse::<Shape>(), Ok(Shape { rows: vec![0b11, 0b10], width: 2 }));
+        assert_eq!("#x".parse::<Shape>(), Err("Unexpected character 'x'".to_owned()));
+        assert_eq!("...".parse::<Shape>(), Err("Shape has no rock".to_owned()));
+        assert_eq!("  .#\n  ##\n".parse::<Shape>(), Ok(Shape { rows: vec![0b11, 0b10], width: 2 }));
+        assert_eq!("\t.#\n\t##".parse::<Shape>(), Ok(Shape { rows: vec![0b11, 0b10], width: 2 }));
+        assert_eq!("  #\n ##".parse::<Shape>(), Err("Unexpected character ' '".to_owned()));
+        assert_eq!("#\n# #".parse::<Shape>(), Err("Unexpected character ' '".to_owned()));
+        assert_eq!("\u{3000}#\n  #".parse::<Shape>(), Err("Unexpected character '\u{3000}'".to_owned()));
+        assert_eq!("  #\u{3000}\n  #".parse::<Shape>(), Err("Unexpected character '\u{3000}'".to_owned()));
+        assert_eq!("#".repeat(64).parse::<Shape>(), Ok(Shape { rows: vec![Row::MAX], width: 64 }));
+        assert!("#".repeat(65).parse::<Shape>().is_err());
+        assert_eq!(parse_shapes("\n#\n\n\n##\n\n"), Ok(vec![
+            Shape { rows: vec![1], width: 1 },
+            Shape { rows: vec![0b11], width: 2 },
+        ]));
+        assert_eq!(parse_shapes("#\n\n#?"), Err("Shape 2: Unexpected character '?'".to_owned()));
+    }
+
+    fn example_data() -> Vec<String> {
+        vec![">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>".to_owned()]
+    }
+
+    #[test]
+    fn cycle_test() -> Result<(), String> {
+        let moves = parse_input(&example_data())?;
+        for rocks in [1, 5, 10, 100, 1000, 2021, 5000] {
+            let mut tower = Tower::new(moves.clone(), ChamberConfig::default())?;
+            for _ in 0..rocks {
+                tower.fall()?;
+            }
+            assert_eq!(tower_height(moves.clone(), ChamberConfig::default(), rocks)?, tower.height() as u64,
+                       "{} rocks", rocks);
         }
+
+        // random jet patterns, extrapolated heights have to match a plain simulation
+        let mut rng = Rng::new(17);
+        for _ in 0..200 {
+            let moves = (0..1 + rng.below(24))
+                .map(|_| if rng.below(2) == 0 { Move::Left } else { Move::Right })
+                .collect::<Vec<_>>();
+            let mut tower = Tower::new(moves.clone(), ChamberConfig::default())?;
+            for _ in 0..2000 {
+                tower.fall()?;
+            }
+            assert_eq!(tower_height(moves.clone(), ChamberConfig::default(), 2000)?, tower.height() as u64,
+                       "{:?}", moves);
+        }
+
+        // the right column never closes, only the top of the tower can tell the cycle
+        let mut tower = Tower::new(vec![Move::Left], ChamberConfig::default())?;
+        let mut heights = vec![];
+        for _ in 0..2 {
+            for _ in 0..5000 {
+                tower.fall()?;
+            }
+            heights.push(tower.height() as u64);
+        }
+        let growth = (heights[1] - heights[0]) / 1000;
+        assert_eq!(tower_height(vec![Move::Left], ChamberConfig::default(), 1000000000000)?,
+                   heights[0] + (1000000000000 - 5000) / 5 * growth);
+
+        // jets that never wrap within the limit leave nothing to repeat
+        let moves = (0..8 * CYCLE_LIMIT).map(|_| if rng.below(2) == 0 { Move::Left } else { Move::Right }).collect();
+        assert_eq!(tower_height(moves, ChamberConfig::default(), 1000000000000),
+                   Err(format!("No cycle found within {} rocks", CYCLE_LIMIT)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn tower_test() -> Result<(), String> {
+        let mut tower = Tower::new(parse_input(&example_data())?, ChamberConfig::default())?;
+        for _ in 0..10 {
+            tower.fall()?;
+        }
+        assert_eq!(format!("{:?}", tower), "\
+            ....#..\n....#..\n....##.\n##..##.\n######.\n.###...\n..#....\n\
+            .####..\n....##.\n....##.\n....#..\n..#.#..\n..#.#..\n#####..\n\
+            ..###..\n...#...\n..####.\n");
+        assert_eq!(tower.height(), 17);
+
+        for _ in 10..2022 {
+            tower.fall()?;
+            assert!(tower.rows.len() < 64);
+        }
+        assert_eq!(tower.height(), 3068);
+        assert!(tower.floor > 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_column_test() -> Result<(), String> {
+        // jets only push left, the right column stays open down to the ground
+        let mut tower = Tower::new(vec![Move::Left], ChamberConfig::default())?;
+        let mut unbounded = Tower::new(vec![Move::Left], ChamberConfig::default())?;
+        unbounded.depth = usize::MAX;
+        for _ in 0..1000 {
+            tower.fall()?;
+            unbounded.fall()?;
+            assert!(tower.rows.len() <= MAX_ROWS);
+            assert_eq!(tower.height(), unbounded.height());
+        }
+        assert!(tower.cut);
+        assert_eq!(unbounded.floor, 0);
+
+        // ten rocks stack in the left column, the eleventh is pushed right and falls to the ground
+        let moves = parse_input(&vec!["<".repeat(10) + ">"])?;
+        let config = ChamberConfig { width: 2, spawn_x: 0, spawn_gap: 0, shapes: parse_shapes("#")? };
+        let mut tower = Tower::new(moves.clone(), config.clone())?;
+        for _ in 0..11 {
+            tower.fall()?;
+        }
+        assert_eq!(tower.height(), 10);
+        assert_eq!(tower.row(0), 0b11);
+        let mut tower = Tower::new(moves, config)?;
+        tower.depth = 8;
+        for _ in 0..10 {
+            tower.fall()?;
+        }
+        assert!(tower.cut);
+        assert_eq!(tower.fall(), Err("Rock 11 falls deeper than the 8 kept rows".to_owned()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_test() -> Result<(), String> {
+        let moves = parse_input(&example_data())?;
+        let config = |width, spawn_x, spawn_gap, shapes: &str| -> Result<ChamberConfig, String> {
+            Ok(ChamberConfig { width, spawn_x, spawn_gap, shapes: parse_shapes(shapes)? })
+        };
+
+        // a single column only stacks, jets never move the rocks
+        assert_eq!(tower_height(moves.clone(), config(1, 0, 3, "#\n#")?, 1000)?, 2000);
+        // full width rows never leave room for anything to slide past
+        assert_eq!(tower_height(moves.clone(), config(8, 0, 5, "########")?, 12345)?, 12345);
+        // squares in a four wide chamber either pair up or stack
+        let squares = tower_height(moves.clone(), config(4, 1, 3, "##\n##")?, 100)?;
+        assert!((100..=200).contains(&squares));
+        assert_ne!(tower_height(moves.clone(), ChamberConfig { spawn_gap: 1, ..ChamberConfig::default() }, 2022)?, 3068);
+
+        assert_eq!(tower_height(moves.clone(), config(64, 0, 5, &"#".repeat(64))?, 999)?, 999);
+        // the standard rocks in a wide chamber, with the cycle extrapolation against a plain simulation
+        let wide = ChamberConfig { width: 40, spawn_x: 10, ..ChamberConfig::default() };
+        let mut tower = Tower::new(moves.clone(), wide.clone())?;
+        for _ in 0..3000 {
+            tower.fall()?;
+        }
+        assert_eq!(tower_height(moves.clone(), wide, 3000)?, tower.height() as u64);
+
+        assert!(tower_height(moves.clone(), config(65, 0, 3, "#")?, 1).is_err());
+        assert!(tower_height(moves.clone(), config(3, 2, 3, "##")?, 1).is_err());
+        assert!(tower_height(moves.clone(), config(3, 0, 3, "")?, 1).is_err());
+        assert!(tower_height(vec![], ChamberConfig::default(), 1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn example_test() -> Result<(), String> {
+        let moves = parse_input(&example_data())?;
+        assert_eq!(task01(moves.clone())?, 3068);
+        assert_eq!(task02(moves)?, 1514285714288);
+
+        Ok(())
     }
 
     #[test]
     fn task01_test() -> Result<(), String> {
         let input_data = input_data();
         let valves = parse_input(&input_data)?;
-        println!("task01: {}", task01(valves));
+        println!("task01: {}", task01(valves)?);
 
         Ok(())
     }
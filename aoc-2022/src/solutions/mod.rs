@@ -1,8 +1,8 @@
 mod day12;
-mod day13;
-mod day14;
+pub mod day13;
+pub mod day14;
 pub mod day15;
-mod day16;
+pub mod day16;
 pub mod day17;
 mod day18;
 mod day19;
@@ -11,4 +11,6 @@ mod day21;
 mod day22;
 mod day23;
 mod day24;
-mod day25;
\ No newline at end of file
+mod day25;
+#[cfg(test)]
+mod rng;
\ No newline at end of file
@@ -0,0 +1,28 @@
+use std::fmt::Debug;
+
+/// Seeded splitmix64 generator for the randomised tests, the same seed always yields
+/// the same cases.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Value in `0..bound` of the bound's own integer type.
+    pub fn below<T>(&mut self, bound: T) -> T
+        where T: TryFrom<u64> + TryInto<u64>,
+              <T as TryFrom<u64>>::Error: Debug,
+              <T as TryInto<u64>>::Error: Debug {
+        let bound = bound.try_into().expect("bound has to be positive");
+        T::try_from(self.next_u64() % bound).unwrap()
+    }
+}